@@ -0,0 +1,72 @@
+//! Compares ways of walking a Ruby hash's entries while deserializing it.
+//!
+//! `keys_and_aref` is how `serde_magnus` used to iterate hashes: fetch `hash.keys`, then look up
+//! each key's value. `foreach` collects keys and values in a single `rb_hash_foreach` pass, as
+//! `serde_magnus` does now.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use magnus::{eval, r_hash::ForEach, Error, RArray, RHash, Ruby, Value};
+use serde_magnus::deserialize;
+use std::collections::HashMap;
+
+fn keys_and_aref(hash: RHash) -> Result<(), Error> {
+    let keys: RArray = hash.funcall("keys", ())?;
+
+    for index in 0..keys.len() {
+        let key: Value = keys.entry(index as isize)?;
+        black_box(hash.aref::<_, Value>(key)?);
+    }
+
+    Ok(())
+}
+
+fn foreach(ruby: &Ruby, hash: RHash) -> Result<(), Error> {
+    let entries = ruby.ary_new_capa(hash.len() * 2);
+
+    hash.foreach(|key: Value, value: Value| {
+        entries.push(key)?;
+        entries.push(value)?;
+        Ok(ForEach::Continue)
+    })?;
+
+    for index in 0..entries.len() {
+        black_box(entries.entry::<Value>(index as isize)?);
+    }
+
+    Ok(())
+}
+
+fn bench_hashes(criterion: &mut Criterion) {
+    let ruby = unsafe { magnus::embed::init() };
+
+    let mut group = criterion.benchmark_group("hashes");
+
+    for size in [100, 10_000, 100_000] {
+        let hash: RHash = eval!(&ruby, "(0...size).to_h { |i| [\"key#{i}\", i] }", size).unwrap();
+
+        group.bench_with_input(
+            BenchmarkId::new("keys_and_aref", size),
+            &hash,
+            |bencher, &hash| bencher.iter(|| keys_and_aref(hash).unwrap()),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("foreach", size),
+            &hash,
+            |bencher, &hash| bencher.iter(|| foreach(&ruby, hash).unwrap()),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("deserialize", size),
+            &hash,
+            |bencher, &hash| {
+                bencher.iter(|| deserialize::<_, HashMap<String, i64>>(&ruby, hash).unwrap())
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_hashes);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+//! Compares ways of getting the Symbols for struct field names while serializing.
+//!
+//! `to_symbol` is how `serde_magnus` used to do it: look up each field's name in Ruby's symbol
+//! table for every struct. `serialize` looks each one up once per call and caches it by the
+//! name's address, as `serde_magnus` does now.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use magnus::{Error, RArray, Ruby};
+use serde::Serialize;
+use serde_magnus::serialize;
+
+#[derive(Serialize)]
+struct Row {
+    id: u64,
+    name: String,
+    email_address: String,
+    active: bool,
+}
+
+fn to_symbol(ruby: &Ruby, rows: &[Row]) -> Result<RArray, Error> {
+    let array = ruby.ary_new_capa(rows.len());
+
+    for row in rows {
+        let hash = ruby.hash_new();
+        hash.aset(ruby.to_symbol("id"), row.id)?;
+        hash.aset(ruby.to_symbol("name"), row.name.as_str())?;
+        hash.aset(ruby.to_symbol("email_address"), row.email_address.as_str())?;
+        hash.aset(ruby.to_symbol("active"), row.active)?;
+        array.push(hash)?;
+    }
+
+    Ok(array)
+}
+
+fn bench_symbols(criterion: &mut Criterion) {
+    let ruby = unsafe { magnus::embed::init() };
+
+    let mut group = criterion.benchmark_group("symbols");
+
+    for size in [100, 10_000, 100_000] {
+        let rows: Vec<Row> = (0..size)
+            .map(|id| Row {
+                id,
+                name: format!("User {}", id),
+                email_address: format!("user{}@example.com", id),
+                active: id % 2 == 0,
+            })
+            .collect();
+
+        group.bench_with_input(
+            BenchmarkId::new("to_symbol", size),
+            &rows,
+            |bencher, rows| bencher.iter(|| black_box(to_symbol(&ruby, rows).unwrap())),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("serialize", size),
+            &rows,
+            |bencher, rows| {
+                bencher.iter(|| black_box(serialize::<_, RArray>(&ruby, rows).unwrap()))
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_symbols);
+criterion_main!(benches);
@@ -0,0 +1,9 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Declare the Ruby version cfgs set by rb_sys_env so rustc doesn't warn about them.
+    println!("cargo:rustc-check-cfg=cfg(ruby_lt_3_2)");
+    println!("cargo:rustc-check-cfg=cfg(ruby_gte_3_2)");
+
+    let _ = rb_sys_env::activate()?;
+
+    Ok(())
+}
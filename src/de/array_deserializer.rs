@@ -1,35 +1,84 @@
-use super::{array_enumerator::ArrayEnumerator, Deserializer};
-use crate::error::Error;
+use super::{array_enumerator::ArrayEnumerator, context::Context, Deserializer};
+use crate::error::{Error, PathSegment};
 use magnus::{RArray, Ruby};
 use serde::de::{DeserializeSeed, SeqAccess};
 
-pub struct ArrayDeserializer<'r> {
+pub struct ArrayDeserializer<'r, 'i> {
     ruby: &'r Ruby,
     entries: ArrayEnumerator<'r>,
+    skippable: bool,
+    resumption: Option<usize>,
+    context: &'r Context<'i>,
 }
 
-impl<'r> ArrayDeserializer<'r> {
-    pub fn new(ruby: &'r Ruby, array: RArray) -> ArrayDeserializer<'r> {
-        ArrayDeserializer {
-            ruby,
-            entries: ArrayEnumerator::new(ruby, array),
+impl<'r, 'i> ArrayDeserializer<'r, 'i> {
+    /// Creates a deserializer for an array's elements. When collecting errors, an element of a
+    /// `skippable` array that fails is skipped on later attempts, along with those before it.
+    /// Otherwise, as for a tuple, the array is skipped whole, unless the error is about a value
+    /// within the element that will be skipped. Then every element is deserialized again.
+    pub fn new(
+        ruby: &'r Ruby,
+        array: RArray,
+        skippable: bool,
+        context: &'r Context<'i>,
+    ) -> Result<ArrayDeserializer<'r, 'i>, Error> {
+        let resumption = context.resumption();
+        let mut entries = ArrayEnumerator::new(ruby, array);
+
+        match resumption {
+            // The array was counted on the attempt that failed within it.
+            Some(index) if skippable => entries.skip_to(index),
+            Some(_) => {}
+            None => context.check_array(ruby, array.len())?,
         }
+
+        Ok(ArrayDeserializer {
+            ruby,
+            entries,
+            skippable,
+            resumption,
+            context,
+        })
     }
 }
 
-impl<'r, 'i> SeqAccess<'i> for ArrayDeserializer<'r> {
+impl<'r, 'i> SeqAccess<'i> for ArrayDeserializer<'r, 'i> {
     type Error = Error;
 
     fn next_element_seed<Seed>(&mut self, seed: Seed) -> Result<Option<Seed::Value>, Self::Error>
     where
         Seed: DeserializeSeed<'i>,
     {
+        self.context.check_interrupts(self.ruby)?;
+
+        let index = self.entries.index();
+
         match self.entries.next() {
-            Some(Ok(entry)) => seed
-                .deserialize(Deserializer::new(self.ruby, entry))
-                .map(Some),
+            Some(Ok(entry)) => {
+                self.context.reenter(self.resumption == Some(index));
+
+                let output = {
+                    let _descent = self.context.descend(|| PathSegment::Index(index));
+                    seed.deserialize(Deserializer::new(self.ruby, entry, self.context))
+                };
+
+                output.map(Some).map_err(|error| {
+                    let error = error.at(PathSegment::Index(index));
+
+                    if self.skippable || error.is_skipped() {
+                        self.context.fail(index, error)
+                    } else {
+                        error
+                    }
+                })
+            }
+
             Some(Err(error)) => Err(error.into()),
             None => Ok(None),
         }
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.remaining())
+    }
 }
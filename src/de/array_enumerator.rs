@@ -1,9 +1,13 @@
-use magnus::{Error, RArray, Ruby, Value};
+use magnus::{value::Lazy, Error, Proc, RArray, Ruby, Value};
 use std::convert::TryInto;
 use tap::TapFallible;
 
 /// For our purposes, stepping through an array by index is faster than `magnus::Enumerator`.
-/// This is due to the fiber overhead of Ruby enumerators.
+/// This is due to the fiber overhead of Ruby enumerators. Other objects that respond to `each`
+/// are first collected into an array by [`collect_each`], when enumerating them is enabled.
+///
+/// Reading an entry calls no Ruby code, so it can only fail if the array's length doesn't fit
+/// in an `isize`. Errors from it are passed on, but no input can provoke one.
 pub struct ArrayEnumerator<'r> {
     ruby: &'r Ruby,
     array: RArray,
@@ -19,6 +23,21 @@ impl<'r> ArrayEnumerator<'r> {
         }
     }
 
+    /// Returns the index of the next element.
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    /// Skips ahead to the element at `index`.
+    pub fn skip_to(&mut self, index: usize) {
+        self.index = self.index.max(index.try_into().unwrap_or(isize::MAX));
+    }
+
+    /// Returns the number of elements not yet enumerated.
+    pub fn remaining(&self) -> usize {
+        self.array.len().saturating_sub(self.index as usize)
+    }
+
     fn current(&self) -> Result<Option<Value>, Error> {
         if let Ok(len) = self.array.len().try_into() {
             if self.index < len {
@@ -48,3 +67,30 @@ impl<'r> Iterator for ArrayEnumerator<'r> {
             .transpose()
     }
 }
+
+/// Collects the values `object` yields to `each` into an array, as `Enumerable#to_a` would.
+///
+/// Once the array holds more than `limit` values, enumeration stops early and the caller is left
+/// to reject the array, so that endless enumerators end. The block that breaks out of `each` is
+/// Ruby code, so that no Rust frames are unwound by it.
+pub fn collect_each(ruby: &Ruby, object: Value, limit: usize) -> Result<RArray, Error> {
+    ruby.get_inner(&COLLECT_EACH).call((object, limit))
+}
+
+static COLLECT_EACH: Lazy<Proc> = Lazy::new(|ruby| {
+    ruby.eval(
+        r#"
+        lambda do |object, limit|
+          array = []
+
+          object.each do |*values|
+            array << (values.size > 1 ? values : values.first)
+            break if array.size > limit
+          end
+
+          array
+        end
+        "#,
+    )
+    .unwrap()
+});
@@ -0,0 +1,36 @@
+use super::{pins::Pins, Context, DeserializeOptions, Deserializer};
+use magnus::{error::IntoError, Error, Ruby, Value};
+use serde::Deserialize;
+
+/// A Ruby value that Rust data may borrow from.
+///
+/// Passed to the function given to [`crate::deserialize_borrowed`] or
+/// [`crate::deserialize_borrowed_with`]. See there for details.
+pub struct Borrowed<'r> {
+    ruby: &'r Ruby,
+    input: Value,
+    options: DeserializeOptions,
+    pins: Pins,
+}
+
+impl<'r> Borrowed<'r> {
+    pub(crate) fn new(ruby: &'r Ruby, input: Value, options: DeserializeOptions) -> Borrowed<'r> {
+        Borrowed {
+            ruby,
+            input,
+            options,
+            pins: Pins::default(),
+        }
+    }
+
+    /// Deserialize the borrowed Ruby value to a Rust type, which may borrow strings and byte
+    /// slices from it.
+    pub fn deserialize<'i, Output>(&'i self) -> Result<Output, Error>
+    where
+        Output: Deserialize<'i>,
+    {
+        let context = Context::new(Some(&self.pins), self.options.clone());
+        Output::deserialize(Deserializer::new(self.ruby, self.input, &context))
+            .map_err(|error| error.into_error(self.ruby))
+    }
+}
@@ -0,0 +1,428 @@
+use super::{pins::Pins, DeserializeOptions};
+use crate::{
+    error::{Error, ErrorKind, PathSegment},
+    interrupts::Interrupts,
+    strings::symbol_name,
+};
+use magnus::{value::ReprValue, RHash, RString, Ruby, Symbol, Value};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+};
+
+/// State shared by the deserializers taking part in a single call to [`crate::deserialize_with`].
+pub struct Context<'i> {
+    pins: Option<&'i Pins>,
+    options: DeserializeOptions,
+    interrupts: Interrupts,
+    depth: Cell<usize>,
+    elements: Cell<usize>,
+    visiting: Cell<Option<RHash>>,
+    collecting: bool,
+    resumptions: RefCell<HashMap<Vec<PathSegment>, usize>>,
+    resuming: Cell<bool>,
+    conversions: Cell<Option<RHash>>,
+    ignored: Option<RefCell<Vec<Vec<PathSegment>>>>,
+    ignored_at: Cell<Option<usize>>,
+    path: RefCell<Vec<PathSegment>>,
+}
+
+impl<'i> Context<'i> {
+    pub fn new(pins: Option<&'i Pins>, options: DeserializeOptions) -> Context<'i> {
+        Context {
+            pins,
+            options,
+            interrupts: Interrupts::default(),
+            depth: Cell::new(0),
+            elements: Cell::new(0),
+            visiting: Cell::new(None),
+            collecting: false,
+            resumptions: RefCell::default(),
+            resuming: Cell::new(false),
+            conversions: Cell::new(None),
+            ignored: None,
+            ignored_at: Cell::new(None),
+            path: RefCell::default(),
+        }
+    }
+
+    /// Prepares the context to be used for repeated attempts at deserializing the same input,
+    /// each resuming where the last one failed. See [`crate::deserialize_collecting`].
+    pub fn collecting(mut self) -> Context<'i> {
+        self.collecting = true;
+        self
+    }
+
+    /// Prepares for another attempt, resuming from the root. The count of elements is kept, as
+    /// the arrays and hashes counted on earlier attempts aren't counted again.
+    pub fn restart(&self) {
+        self.ignored_at.set(None);
+        self.resuming.set(true);
+    }
+
+    /// Called when creating a deserializer for an array or hash. If an earlier attempt failed
+    /// within it, returns the position of the element or entry that failed, or of the one after
+    /// it if that was skipped.
+    pub fn resumption(&self) -> Option<usize> {
+        if !self.resuming.replace(false) {
+            return None;
+        }
+
+        self.resumptions
+            .borrow()
+            .get(self.path.borrow().as_slice())
+            .copied()
+    }
+
+    /// Called before deserializing an element or entry, with whether it's the one an earlier
+    /// attempt failed within, so that it resumes from there too.
+    pub fn reenter(&self, reentering: bool) {
+        self.resuming.set(reentering);
+    }
+
+    /// Called when the element or entry at `position`, in the array or hash being deserialized,
+    /// fails. When collecting errors, later attempts resume at it if the error is about a value
+    /// within it that will be skipped, or after it otherwise. Exceptions raised by Ruby stop
+    /// collecting errors, so they're passed on as they are.
+    pub fn fail(&self, position: usize, error: Error) -> Error {
+        if error.is_skipped() {
+            self.resume_at(position, error)
+        } else {
+            self.resume_at(position + 1, error)
+        }
+    }
+
+    /// Called when a key in the hash being deserialized fails. Its entry is skipped whole.
+    pub fn fail_key(&self, position: usize, error: Error) -> Error {
+        self.resume_at(position + 1, error)
+    }
+
+    fn resume_at(&self, position: usize, error: Error) -> Error {
+        if !self.collecting || error.kind() == ErrorKind::RubyException {
+            return error;
+        }
+
+        self.resumptions
+            .borrow_mut()
+            .insert(self.path.borrow().clone(), position);
+
+        error.skipping()
+    }
+
+    /// Records the paths to values the output ignores. See
+    /// [`crate::deserialize_reporting_ignored`].
+    pub fn reporting_ignored(mut self) -> Context<'i> {
+        self.ignored = Some(RefCell::default());
+        self
+    }
+
+    /// Returns whether the paths to ignored values are being recorded.
+    pub fn reports_ignored(&self) -> bool {
+        self.ignored.is_some()
+    }
+
+    /// Called when the output ignores the value being deserialized.
+    pub fn ignore(&self) {
+        self.ignored_at.set(Some(self.depth.get()));
+
+        if let Some(ignored) = &self.ignored {
+            ignored.borrow_mut().push(self.path.borrow().clone());
+        }
+    }
+
+    /// Returns whether the output ignored the value just deserialized, and forgets it. Values
+    /// ignored within that value, such as entries of a hash it holds, are ignored at a greater
+    /// depth, so they don't count.
+    pub fn ignored(&self) -> bool {
+        self.ignored_at.take() == Some(self.depth.get())
+    }
+
+    /// Returns the paths to the values the output ignored, in the order they were ignored.
+    pub fn into_ignored(self) -> Vec<Vec<PathSegment>> {
+        self.ignored.map(RefCell::into_inner).unwrap_or_default()
+    }
+
+    /// Whether the path to the value being deserialized is needed, to resume after errors or to
+    /// report ignored ones.
+    fn tracks_path(&self) -> bool {
+        self.collecting || self.ignored.is_some()
+    }
+
+    /// Returns the pins that strings may be borrowed from, if the output may borrow.
+    pub fn pins(&self) -> Option<&'i Pins> {
+        self.pins
+    }
+
+    pub fn options(&self) -> &DeserializeOptions {
+        &self.options
+    }
+
+    /// Converts `value` with `convert`, or, when collecting errors, returns the result of
+    /// converting it on an earlier attempt. `method` names the conversion, such as `to_hash`.
+    /// Returns `None` if `convert` does.
+    pub fn convert<Convert>(
+        &self,
+        ruby: &Ruby,
+        value: Value,
+        method: &str,
+        convert: Convert,
+    ) -> Result<Option<Value>, Error>
+    where
+        Convert: FnOnce() -> Result<Option<Value>, Error>,
+    {
+        if !self.collecting {
+            return convert();
+        }
+
+        let conversions = match self.conversions.get() {
+            Some(conversions) => conversions,
+            None => {
+                let conversions = ruby.hash_new();
+                let _: Value = conversions.funcall("compare_by_identity", ())?;
+                self.conversions.set(Some(conversions));
+                conversions
+            }
+        };
+
+        let by_method = match conversions.get(value).and_then(RHash::from_value) {
+            Some(by_method) => by_method,
+            None => {
+                let by_method = ruby.hash_new();
+                conversions.aset(value, by_method)?;
+                by_method
+            }
+        };
+
+        let method = ruby.to_symbol(method);
+
+        if let Some(converted) = by_method.get(method) {
+            return Ok(Some(converted));
+        }
+
+        let converted = convert()?;
+
+        if let Some(converted) = converted {
+            by_method.aset(method, converted)?;
+        }
+
+        Ok(converted)
+    }
+
+    /// Called once per element of an array or hash, to let Ruby interrupt long conversions.
+    pub fn check_interrupts(&self, ruby: &Ruby) -> Result<(), Error> {
+        Ok(self.interrupts.check(ruby)?)
+    }
+
+    /// Called before deserializing the contents of an array or hash. Fails if doing so would
+    /// exceed the maximum depth or, if cycles are being detected, if the array or hash is
+    /// already being deserialized. The returned guard must be held until its contents are done.
+    pub fn enter(&self, ruby: &Ruby, value: Value) -> Result<Nesting<'_, 'i>, Error> {
+        let depth = self.depth.get() + 1;
+
+        if self.options.max_depth.map_or(false, |max| depth > max) {
+            return Err(nesting_too_deep(ruby, depth));
+        }
+
+        let visiting = if self.options.detect_cycles {
+            let visiting = self.visiting(ruby)?;
+
+            if visiting.get(value).is_some() {
+                return Err(Error::new(
+                    ruby.exception_arg_error(),
+                    format!(
+                        "can't deserialize recursive {}",
+                        unsafe { value.classname() }.into_owned()
+                    ),
+                ));
+            }
+
+            visiting.aset(value, true)?;
+            Some(visiting)
+        } else {
+            None
+        };
+
+        self.depth.set(depth);
+
+        Ok(Nesting {
+            context: self,
+            value,
+            visiting,
+        })
+    }
+
+    /// Called before deserializing the element or entry at `segment`. Where the path to it is
+    /// needed, tracks it until the returned guard is dropped.
+    pub fn descend<Segment>(&self, segment: Segment) -> Descent<'_, 'i>
+    where
+        Segment: FnOnce() -> PathSegment,
+    {
+        if !self.tracks_path() {
+            return Descent { context: None };
+        }
+
+        self.path.borrow_mut().push(segment());
+        Descent {
+            context: Some(self),
+        }
+    }
+
+    /// Called before deserializing an array's elements, to enforce the limits on its length and
+    /// on the total number of elements.
+    pub fn check_array(&self, ruby: &Ruby, len: usize) -> Result<(), Error> {
+        if let Some(max_array_length) = self.options.max_array_length {
+            if len > max_array_length {
+                return Err(Error::new(
+                    ruby.exception_arg_error(),
+                    format!(
+                        "array length {} exceeds the limit of {}",
+                        len, max_array_length
+                    ),
+                ));
+            }
+        }
+
+        self.count_elements(ruby, len)
+    }
+
+    /// Returns the most values an object may yield to `each` without exceeding the limit on
+    /// enumerated values, or those on array length and on the total number of elements.
+    pub fn enumeration_limit(&self) -> usize {
+        let remaining = self
+            .options
+            .max_elements
+            .map_or(usize::MAX, |max_elements| {
+                max_elements.saturating_sub(self.elements.get())
+            });
+
+        self.options
+            .max_enumerated
+            .min(self.options.max_array_length.unwrap_or(usize::MAX))
+            .min(remaining)
+    }
+
+    /// Called after enumerating an object, to enforce the limit on the number of values taken
+    /// from it.
+    pub fn check_enumerated(&self, ruby: &Ruby, len: usize) -> Result<(), Error> {
+        if len > self.options.max_enumerated {
+            return Err(Error::new(
+                ruby.exception_arg_error(),
+                format!(
+                    "enumeration yielded more than {} values",
+                    self.options.max_enumerated
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Called before deserializing a hash's entries, to enforce the limits on its size and on the
+    /// total number of elements.
+    pub fn check_hash(&self, ruby: &Ruby, len: usize) -> Result<(), Error> {
+        if let Some(max_hash_size) = self.options.max_hash_size {
+            if len > max_hash_size {
+                return Err(Error::new(
+                    ruby.exception_arg_error(),
+                    format!("hash size {} exceeds the limit of {}", len, max_hash_size),
+                ));
+            }
+        }
+
+        self.count_elements(ruby, len)
+    }
+
+    /// Called before deserializing a string, to enforce the limit on its length.
+    pub fn check_string(&self, ruby: &Ruby, string: RString) -> Result<(), Error> {
+        if let Some(max_string_bytes) = self.options.max_string_bytes {
+            if string.len() > max_string_bytes {
+                return Err(Error::new(
+                    ruby.exception_arg_error(),
+                    format!(
+                        "string of {} bytes exceeds the limit of {}",
+                        string.len(),
+                        max_string_bytes
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Called before deserializing a Symbol's name, to enforce the limit on string length.
+    pub fn check_symbol(&self, ruby: &Ruby, symbol: Symbol) -> Result<(), Error> {
+        if self.options.max_string_bytes.is_none() {
+            return Ok(());
+        }
+
+        self.check_string(ruby, symbol_name(symbol))
+    }
+
+    fn count_elements(&self, ruby: &Ruby, len: usize) -> Result<(), Error> {
+        let elements = self.elements.get().saturating_add(len);
+
+        if let Some(max_elements) = self.options.max_elements {
+            if elements > max_elements {
+                return Err(Error::new(
+                    ruby.exception_arg_error(),
+                    format!("input has more than {} elements", max_elements),
+                ));
+            }
+        }
+
+        self.elements.set(elements);
+        Ok(())
+    }
+
+    /// Returns the set of arrays and hashes being deserialized, compared by identity so that
+    /// recursive ones can be looked up without hashing their contents.
+    fn visiting(&self, ruby: &Ruby) -> Result<RHash, Error> {
+        if let Some(visiting) = self.visiting.get() {
+            return Ok(visiting);
+        }
+
+        let visiting = ruby.hash_new();
+        let _: Value = visiting.funcall("compare_by_identity", ())?;
+        self.visiting.set(Some(visiting));
+        Ok(visiting)
+    }
+}
+
+/// Marks an array or hash as being deserialized until dropped. See [`Context::enter`].
+pub struct Nesting<'c, 'i> {
+    context: &'c Context<'i>,
+    value: Value,
+    visiting: Option<RHash>,
+}
+
+impl<'c, 'i> Drop for Nesting<'c, 'i> {
+    fn drop(&mut self) {
+        self.context.depth.set(self.context.depth.get() - 1);
+
+        if let Some(visiting) = self.visiting {
+            let _: Result<Option<Value>, magnus::Error> = visiting.delete(self.value);
+        }
+    }
+}
+
+/// Marks the path to a value as the one being deserialized until dropped. See
+/// [`Context::descend`].
+pub struct Descent<'c, 'i> {
+    context: Option<&'c Context<'i>>,
+}
+
+impl<'c, 'i> Drop for Descent<'c, 'i> {
+    fn drop(&mut self) {
+        if let Some(context) = self.context {
+            context.path.borrow_mut().pop();
+        }
+    }
+}
+
+fn nesting_too_deep(ruby: &Ruby, depth: usize) -> Error {
+    Error::new(
+        ruby.exception_arg_error(),
+        format!("nesting of {} is too deep", depth),
+    )
+}
@@ -1,25 +1,294 @@
 use magnus::{
     value::{Qfalse, Qtrue, ReprValue},
-    Fixnum, Float, RArray, RBignum, RHash, RString, Ruby, Symbol, Value,
+    Fixnum, Float, Integer, RArray, RBignum, RHash, RString, Ruby, Symbol, TryConvert, Value,
 };
 
-use serde::forward_to_deserialize_any;
+use serde::{de::Unexpected, forward_to_deserialize_any};
 
-use super::{ArrayDeserializer, EnumDeserializer, HashDeserializer};
-use crate::error::Error;
+use super::{
+    array_enumerator::collect_each, context::Context, ArrayDeserializer, EnumDeserializer,
+    HashDeserializer, StructDeserializer,
+};
+use crate::{
+    error::{classify, Error, ErrorKind},
+    strings::symbol_name,
+};
 
-pub struct Deserializer<'r> {
+pub struct Deserializer<'r, 'i> {
     ruby: &'r Ruby,
     value: Value,
+    context: &'r Context<'i>,
 }
 
-impl<'r> Deserializer<'r> {
-    pub fn new(ruby: &'r Ruby, value: Value) -> Deserializer<'r> {
-        Deserializer { ruby, value }
+impl<'r, 'i> Deserializer<'r, 'i> {
+    pub fn new(ruby: &'r Ruby, value: Value, context: &'r Context<'i>) -> Deserializer<'r, 'i> {
+        Deserializer {
+            ruby,
+            value,
+            context,
+        }
+    }
+
+    fn visit_rstring<Visitor>(
+        &self,
+        string: RString,
+        visitor: Visitor,
+    ) -> Result<Visitor::Value, Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        self.context.check_string(self.ruby, string)?;
+
+        if let Some(pins) = self.context.pins() {
+            let pinned = pins.pin(string);
+
+            // SAFETY: The pinned string is frozen and can't be collected or moved until `pins`,
+            // which outlives 'i, is dropped.
+            if let Some(borrowed) = unsafe { pinned.test_as_str() } {
+                return visitor.visit_borrowed_str(unsafe { &*(borrowed as *const str) });
+            }
+        } else if let Some(borrowed) = unsafe { string.test_as_str() } {
+            return visitor.visit_str(borrowed);
+        }
+
+        visitor.visit_string(
+            string
+                .to_string()
+                .map_err(classify(ErrorKind::InvalidValue))?,
+        )
+    }
+
+    /// Returns the contents of a String input, without surrounding whitespace, when strings are
+    /// being coerced to numbers and bools.
+    fn coercible_string(&self) -> Result<Option<String>, Error> {
+        if !self.context.options().coerce {
+            return Ok(None);
+        }
+
+        match RString::from_value(self.value) {
+            Some(string) => {
+                self.context.check_string(self.ruby, string)?;
+                let string = string
+                    .to_string()
+                    .map_err(classify(ErrorKind::InvalidValue))?;
+                Ok(Some(string.trim().to_owned()))
+            }
+
+            None => Ok(None),
+        }
+    }
+
+    /// Returns whether the input is one of the core types [`serde::Deserializer::deserialize_any`]
+    /// handles directly.
+    fn is_core_type(&self) -> bool {
+        self.value.is_nil()
+            || Qtrue::from_value(self.value).is_some()
+            || Qfalse::from_value(self.value).is_some()
+            || Integer::from_value(self.value).is_some()
+            || Float::from_value(self.value).is_some()
+            || RString::from_value(self.value).is_some()
+            || Symbol::from_value(self.value).is_some()
+            || RArray::from_value(self.value).is_some()
+            || RHash::from_value(self.value).is_some()
+    }
+
+    /// Converts an input of any other type with its implicit conversion method, such as
+    /// `to_hash`, or, if the options allow, its explicit conversion method, such as `to_h`.
+    fn convert<Output>(&self, implicit: &str, explicit: &str) -> Result<Option<Output>, Error>
+    where
+        Output: TryConvert,
+    {
+        if self.is_core_type() {
+            return Ok(None);
+        }
+
+        let method = if self.value.respond_to(implicit, false)? {
+            implicit
+        } else if self.context.options().explicit_conversions
+            && self.value.respond_to(explicit, false)?
+        {
+            explicit
+        } else {
+            return Ok(None);
+        };
+
+        let converted = self.context.convert(self.ruby, self.value, method, || {
+            Ok(Some(self.value.funcall(method, ())?))
+        })?;
+
+        converted
+            .map(TryConvert::try_convert)
+            .transpose()
+            .map_err(classify(ErrorKind::InvalidType))
+    }
+
+    /// Deserializes a sequence, converting the input to an Array if it isn't one. If enabled,
+    /// inputs that can't be converted but respond to `each`, such as Sets, Ranges and
+    /// Enumerators, are enumerated instead. See [`Deserializer::visit_array`] for `skippable`.
+    fn deserialize_sequence<Visitor>(
+        self,
+        visitor: Visitor,
+        skippable: bool,
+    ) -> Result<Visitor::Value, Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        let array = match self.convert::<RArray>("to_ary", "to_a")? {
+            Some(array) => Some(array),
+            None if self.is_core_type() => RArray::from_value(self.value),
+
+            None if self.context.options().enumerate && self.value.respond_to("each", false)? => {
+                let collected = self.context.convert(self.ruby, self.value, "each", || {
+                    let limit = self.context.enumeration_limit();
+                    let array = collect_each(self.ruby, self.value, limit)?;
+                    self.context.check_enumerated(self.ruby, array.len())?;
+                    Ok(Some(array.as_value()))
+                })?;
+
+                collected.and_then(RArray::from_value)
+            }
+
+            None => None,
+        };
+
+        if let Some(array) = array {
+            return self.visit_array(array, visitor, skippable);
+        }
+
+        serde::Deserializer::deserialize_any(self, visitor)
+    }
+
+    /// Deserializes an array's elements. When collecting errors, the elements of a sequence are
+    /// `skippable`, but not those of a tuple, which are told apart by their positions.
+    fn visit_array<Visitor>(
+        &self,
+        array: RArray,
+        visitor: Visitor,
+        skippable: bool,
+    ) -> Result<Visitor::Value, Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        let _nesting = self.context.enter(self.ruby, array.as_value())?;
+        visitor.visit_seq(ArrayDeserializer::new(
+            self.ruby,
+            array,
+            skippable,
+            self.context,
+        )?)
+    }
+
+    /// Deserializes an integer. Numeric Strings are parsed as 64-bit integers, or, if `wide`, as
+    /// 128-bit integers when they don't fit in 64 bits.
+    fn deserialize_integer<Visitor>(
+        self,
+        visitor: Visitor,
+        wide: bool,
+    ) -> Result<Visitor::Value, Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        let options = self.context.options();
+
+        if let Some(float) = Float::from_value(self.value) {
+            let value = float.to_f64();
+
+            if options.strict_numbers {
+                return Err(serde::de::Error::invalid_type(
+                    Unexpected::Float(value),
+                    &visitor,
+                ));
+            }
+
+            if options.coerce && value.fract() == 0.0 {
+                if (i64::MIN as f64..i64::MAX as f64).contains(&value) {
+                    return visitor.visit_i64(value as i64);
+                }
+
+                if (0.0..u64::MAX as f64).contains(&value) {
+                    return visitor.visit_u64(value as u64);
+                }
+            }
+        }
+
+        if let Some(string) = self.coercible_string()? {
+            if let Ok(value) = string.parse() {
+                return visitor.visit_i64(value);
+            }
+
+            if let Ok(value) = string.parse() {
+                return visitor.visit_u64(value);
+            }
+
+            if wide {
+                if let Ok(value) = string.parse() {
+                    return visitor.visit_i128(value);
+                }
+
+                if let Ok(value) = string.parse() {
+                    return visitor.visit_u128(value);
+                }
+            }
+
+            return Err(serde::de::Error::invalid_value(
+                Unexpected::Str(&string),
+                &visitor,
+            ));
+        }
+
+        serde::Deserializer::deserialize_any(self, visitor)
+    }
+
+    /// Returns the number to deserialize into a float, or `None` to deserialize the input as it
+    /// is. In strict mode, Integers are rejected, and in coercion mode, numeric Strings parsed.
+    fn float(&self, expected: &dyn serde::de::Expected) -> Result<Option<f64>, Error> {
+        if let Some(float) = Float::from_value(self.value) {
+            let non_finite_floats = self.context.options().non_finite_floats;
+            return non_finite_floats.apply(self.ruby, float.to_f64());
+        }
+
+        if self.context.options().strict_numbers {
+            if let Some(integer) = Integer::from_value(self.value) {
+                let unexpected = match integer.to_i64() {
+                    Ok(value) => Unexpected::Signed(value),
+                    Err(_) => match integer.to_u64() {
+                        Ok(value) => Unexpected::Unsigned(value),
+                        Err(_) => Unexpected::Other("integer"),
+                    },
+                };
+
+                return Err(serde::de::Error::invalid_type(unexpected, expected));
+            }
+        }
+
+        if let Some(string) = self.coercible_string()? {
+            return match string.parse::<f64>() {
+                Ok(value) if value.is_finite() => Ok(Some(value)),
+                _ => Err(serde::de::Error::invalid_value(
+                    Unexpected::Str(&string),
+                    expected,
+                )),
+            };
+        }
+
+        Ok(None)
     }
 }
 
-impl<'r, 'i> serde::Deserializer<'i> for Deserializer<'r> {
+macro_rules! deserialize_integers {
+    ($($method:ident)*) => {
+        $(
+            fn $method<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
+            where
+                Visitor: serde::de::Visitor<'i>,
+            {
+                self.deserialize_integer(visitor, false)
+            }
+        )*
+    };
+}
+
+impl<'r, 'i> serde::Deserializer<'i> for Deserializer<'r, 'i> {
     type Error = Error;
 
     fn deserialize_any<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
@@ -43,27 +312,36 @@ impl<'r, 'i> serde::Deserializer<'i> for Deserializer<'r> {
         }
 
         if let Some(bignum) = RBignum::from_value(self.value) {
-            return visitor.visit_i64(bignum.to_i64()?);
+            let value = bignum.to_i64().map_err(classify(ErrorKind::InvalidValue))?;
+            return visitor.visit_i64(value);
         }
 
         if let Some(float) = Float::from_value(self.value) {
-            return visitor.visit_f64(float.to_f64());
+            let non_finite_floats = self.context.options().non_finite_floats;
+
+            return match non_finite_floats.apply(self.ruby, float.to_f64())? {
+                Some(value) => visitor.visit_f64(value),
+                None => visitor.visit_unit(),
+            };
         }
 
         if let Some(string) = RString::from_value(self.value) {
-            return visitor.visit_str(string.to_string()?.as_str());
+            return self.visit_rstring(string, visitor);
         }
 
         if let Some(symbol) = Symbol::from_value(self.value) {
-            return visitor.visit_str(symbol.name()?.to_string().as_str());
+            self.context.check_symbol(self.ruby, symbol)?;
+            let name = symbol.name().map_err(classify(ErrorKind::InvalidValue))?;
+            return visitor.visit_str(&name);
         }
 
         if let Some(array) = RArray::from_value(self.value) {
-            return visitor.visit_seq(ArrayDeserializer::new(self.ruby, array));
+            return self.visit_array(array, visitor, false);
         }
 
         if let Some(hash) = RHash::from_value(self.value) {
-            return visitor.visit_map(HashDeserializer::new(self.ruby, hash)?);
+            let _nesting = self.context.enter(self.ruby, self.value)?;
+            return HashDeserializer::new(self.ruby, hash, self.context)?.visit(visitor);
         }
 
         Err(Error::new(
@@ -72,17 +350,27 @@ impl<'r, 'i> serde::Deserializer<'i> for Deserializer<'r> {
                 "can't deserialize {}",
                 unsafe { self.value.classname() }.into_owned()
             ),
-        ))
+        )
+        .with_kind(ErrorKind::InvalidType))
     }
 
-    fn deserialize_bytes<Visitor>(self, _visitor: Visitor) -> Result<Visitor::Value, Self::Error>
+    fn deserialize_bytes<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
     where
         Visitor: serde::de::Visitor<'i>,
     {
+        if let (Some(pins), Some(string)) = (self.context.pins(), RString::from_value(self.value)) {
+            self.context.check_string(self.ruby, string)?;
+            let pinned = pins.pin(string);
+
+            // SAFETY: See `visit_rstring`.
+            return visitor.visit_borrowed_bytes(unsafe { &*(pinned.as_slice() as *const [u8]) });
+        }
+
         Err(Error::new(
             self.ruby.exception_type_error(),
             "can't deserialize into byte slice",
-        ))
+        )
+        .with_kind(ErrorKind::InvalidType))
     }
 
     fn deserialize_byte_buf<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
@@ -90,6 +378,7 @@ impl<'r, 'i> serde::Deserializer<'i> for Deserializer<'r> {
         Visitor: serde::de::Visitor<'i>,
     {
         if let Some(string) = RString::from_value(self.value) {
+            self.context.check_string(self.ruby, string)?;
             visitor.visit_byte_buf(unsafe { string.as_slice() }.to_owned())
         } else {
             Err(Error::new(
@@ -98,7 +387,80 @@ impl<'r, 'i> serde::Deserializer<'i> for Deserializer<'r> {
                     "no implicit conversion of {} to String",
                     unsafe { self.value.classname() }.into_owned()
                 ),
-            ))
+            )
+            .with_kind(ErrorKind::InvalidType))
+        }
+    }
+
+    fn deserialize_bool<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        if let Some(string) = self.coercible_string()? {
+            return match string.as_str() {
+                "1" | "t" | "T" | "true" | "TRUE" | "on" | "ON" => visitor.visit_bool(true),
+                "0" | "f" | "F" | "false" | "FALSE" | "off" | "OFF" => visitor.visit_bool(false),
+                _ => Err(serde::de::Error::invalid_value(
+                    Unexpected::Str(&string),
+                    &visitor,
+                )),
+            };
+        }
+
+        self.deserialize_any(visitor)
+    }
+
+    deserialize_integers! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+    }
+
+    fn deserialize_i128<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        self.deserialize_integer(visitor, true)
+    }
+
+    fn deserialize_u128<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        self.deserialize_integer(visitor, true)
+    }
+
+    fn deserialize_f32<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        match self.float(&visitor)? {
+            Some(value) => {
+                let narrowed = value as f32;
+
+                if self.context.options().strict_numbers
+                    && f64::from(narrowed) != value
+                    && !value.is_nan()
+                {
+                    return Err(serde::de::Error::invalid_value(
+                        Unexpected::Float(value),
+                        &"a float exactly representable as f32",
+                    ));
+                }
+
+                visitor.visit_f32(narrowed)
+            }
+
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f64<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        match self.float(&visitor)? {
+            Some(value) => visitor.visit_f64(value),
+            None => self.deserialize_any(visitor),
         }
     }
 
@@ -106,7 +468,16 @@ impl<'r, 'i> serde::Deserializer<'i> for Deserializer<'r> {
     where
         Visitor: serde::de::Visitor<'i>,
     {
-        if self.value.is_nil() {
+        let options = self.context.options();
+
+        let empty_string = options.empty_strings_as_none
+            && RString::from_value(self.value).map_or(false, |string| string.is_empty());
+
+        let non_finite_float = Float::from_value(self.value).map_or(false, |float| {
+            options.non_finite_floats.is_nil(float.to_f64())
+        });
+
+        if self.value.is_nil() || empty_string || non_finite_float {
             visitor.visit_none()
         } else {
             visitor.visit_some(self)
@@ -123,27 +494,40 @@ impl<'r, 'i> serde::Deserializer<'i> for Deserializer<'r> {
         Visitor: serde::de::Visitor<'i>,
     {
         if let Some(variant) = RString::from_value(self.value) {
+            self.context.check_string(self.ruby, variant)?;
             return visitor.visit_enum(EnumDeserializer::new(
                 self.ruby,
-                variant.to_string()?,
+                variant
+                    .to_string()
+                    .map_err(classify(ErrorKind::InvalidValue))?,
                 self.ruby.qnil().as_value(),
+                self.context,
             ));
         }
 
         if let Some(hash) = RHash::from_value(self.value) {
             if hash.len() == 1 {
+                let _nesting = self.context.enter(self.ruby, self.value)?;
                 let keys: RArray = hash.funcall("keys", ())?;
-                let key: String = keys.entry(0)?;
+                let key: RString = keys.entry(0).map_err(classify(ErrorKind::InvalidType))?;
+                self.context.check_string(self.ruby, key)?;
+                let key = key.to_string().map_err(classify(ErrorKind::InvalidValue))?;
                 let value = hash
                     .get(key.as_str())
                     .unwrap_or_else(|| self.ruby.qnil().as_value());
 
-                return visitor.visit_enum(EnumDeserializer::new(self.ruby, key, value));
+                return visitor.visit_enum(EnumDeserializer::new(
+                    self.ruby,
+                    key,
+                    value,
+                    self.context,
+                ));
             } else {
                 return Err(Error::new(
                     self.ruby.exception_type_error(),
                     format!("can't deserialize Hash of length {} to Enum", hash.len()),
-                ));
+                )
+                .with_kind(ErrorKind::InvalidLength));
             }
         }
 
@@ -153,7 +537,69 @@ impl<'r, 'i> serde::Deserializer<'i> for Deserializer<'r> {
                 "can't deserialize {} to Enum",
                 unsafe { self.value.classname() }.into_owned()
             ),
-        ))
+        )
+        .with_kind(ErrorKind::InvalidType))
+    }
+
+    fn deserialize_struct<Visitor>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: Visitor,
+    ) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        if let Some(hash) = self.convert::<RHash>("to_hash", "to_h")? {
+            return Deserializer::new(self.ruby, hash.as_value(), self.context)
+                .deserialize_struct(name, fields, visitor);
+        }
+
+        match RHash::from_value(self.value) {
+            Some(hash) if !fields.is_empty() => {
+                let _nesting = self.context.enter(self.ruby, self.value)?;
+                StructDeserializer::new(self.ruby, hash, fields, self.context)?.visit(visitor)
+            }
+
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_identifier<Visitor>(
+        self,
+        visitor: Visitor,
+    ) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        let string = if let Some(symbol) = Symbol::from_value(self.value) {
+            self.context.check_symbol(self.ruby, symbol)?;
+
+            if let Some(symbol) = symbol.as_static() {
+                if let Ok(name) = symbol.name() {
+                    return visitor.visit_borrowed_str(name);
+                }
+            }
+
+            // A dynamic Symbol's name is a frozen String it holds, so this doesn't allocate.
+            symbol_name(symbol)
+        } else if let Some(string) = RString::from_value(self.value) {
+            self.context.check_string(self.ruby, string)?;
+            string
+        } else {
+            return self.deserialize_any(visitor);
+        };
+
+        if let Some(name) = unsafe { string.test_as_str() } {
+            return visitor.visit_str(name);
+        }
+
+        // Names that can't be converted to UTF-8 are visited as bytes, so that they name no
+        // field rather than failing the struct.
+        match string.to_string() {
+            Ok(name) => visitor.visit_str(&name),
+            Err(_) => visitor.visit_bytes(unsafe { string.as_slice() }),
+        }
     }
 
     fn deserialize_newtype_struct<Visitor>(
@@ -174,12 +620,55 @@ impl<'r, 'i> serde::Deserializer<'i> for Deserializer<'r> {
     where
         Visitor: serde::de::Visitor<'i>,
     {
+        self.context.ignore();
         visitor.visit_unit()
     }
 
+    fn deserialize_seq<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        self.deserialize_sequence(visitor, true)
+    }
+
+    fn deserialize_tuple<Visitor>(
+        self,
+        _len: usize,
+        visitor: Visitor,
+    ) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        self.deserialize_sequence(visitor, false)
+    }
+
+    fn deserialize_tuple_struct<Visitor>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: Visitor,
+    ) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        self.deserialize_sequence(visitor, false)
+    }
+
+    fn deserialize_map<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        if let Some(hash) = self.convert::<RHash>("to_hash", "to_h")? {
+            return Deserializer::new(self.ruby, hash.as_value(), self.context)
+                .deserialize_any(visitor);
+        }
+
+        self.deserialize_any(visitor)
+    }
+
     forward_to_deserialize_any! {
         <Visitor: Visitor<'i>>
-        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        unit unit_struct seq tuple tuple_struct map struct identifier
+        char str string
+        unit unit_struct
     }
 }
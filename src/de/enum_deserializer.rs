@@ -1,33 +1,40 @@
-use super::VariantDeserializer;
+use super::{context::Context, VariantDeserializer};
 use crate::error::Error;
 use magnus::{Ruby, Value};
 use serde::de::{DeserializeSeed, EnumAccess, IntoDeserializer};
 
-pub struct EnumDeserializer<'r> {
+pub struct EnumDeserializer<'r, 'i> {
     ruby: &'r Ruby,
     variant: String,
     value: Value,
+    context: &'r Context<'i>,
 }
 
-impl<'r> EnumDeserializer<'r> {
-    pub fn new(ruby: &'r Ruby, variant: String, value: Value) -> EnumDeserializer<'r> {
+impl<'r, 'i> EnumDeserializer<'r, 'i> {
+    pub fn new(
+        ruby: &'r Ruby,
+        variant: String,
+        value: Value,
+        context: &'r Context<'i>,
+    ) -> EnumDeserializer<'r, 'i> {
         EnumDeserializer {
             ruby,
             variant,
             value,
+            context,
         }
     }
 }
 
-impl<'r, 'i> EnumAccess<'i> for EnumDeserializer<'r> {
-    type Variant = VariantDeserializer<'r>;
+impl<'r, 'i> EnumAccess<'i> for EnumDeserializer<'r, 'i> {
+    type Variant = VariantDeserializer<'r, 'i>;
     type Error = Error;
 
     fn variant_seed<Seed>(self, seed: Seed) -> Result<(Seed::Value, Self::Variant), Error>
     where
         Seed: DeserializeSeed<'i>,
     {
-        let deserializer = VariantDeserializer::new(self.ruby, self.value);
+        let deserializer = VariantDeserializer::new(self.ruby, self.value, self.context);
 
         seed.deserialize(self.variant.into_deserializer())
             .map(|value| (value, deserializer))
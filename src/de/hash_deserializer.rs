@@ -1,42 +1,213 @@
-use super::{array_enumerator::ArrayEnumerator, Deserializer};
-use crate::error::Error;
-use magnus::{value::ReprValue, RHash, Ruby};
+use super::{array_enumerator::ArrayEnumerator, context::Context, Deserializer, DuplicateKeys};
+use crate::{
+    error::{Error, ErrorKind, PathSegment},
+    strings::symbol_name,
+};
+use magnus::{r_hash::ForEach, value::ReprValue, RArray, RHash, RString, Ruby, Symbol, Value};
 use serde::de::{DeserializeSeed, MapAccess};
-use std::iter::Peekable;
+use std::collections::{HashMap, HashSet};
 
-pub struct HashDeserializer<'r> {
+pub struct HashDeserializer<'r, 'i> {
     ruby: &'r Ruby,
-    hash: RHash,
-    keys: Peekable<ArrayEnumerator<'r>>,
+    flattened: RArray,
+    entries: ArrayEnumerator<'r>,
+    key: Option<(usize, Value)>,
+    resumption: Option<usize>,
+    context: &'r Context<'i>,
 }
 
-impl<'r> HashDeserializer<'r> {
-    pub fn new(ruby: &'r Ruby, hash: RHash) -> Result<HashDeserializer<'r>, Error> {
+impl<'r, 'i> HashDeserializer<'r, 'i> {
+    /// Creates a deserializer for a hash's entries. When collecting errors, an entry that fails
+    /// is skipped on later attempts, along with those before it.
+    pub fn new(
+        ruby: &'r Ruby,
+        hash: RHash,
+        context: &'r Context<'i>,
+    ) -> Result<HashDeserializer<'r, 'i>, Error> {
+        let resumption = context.resumption();
+
+        // The hash was counted on the attempt that failed within it.
+        if resumption.is_none() {
+            context.check_hash(ruby, hash.len())?;
+        }
+
+        // Its entries are collected once, rather than on every attempt.
+        let flattened = context.convert(ruby, hash.as_value(), "entries", || {
+            let flattened = entries(ruby, hash, context.options().duplicate_keys)?;
+            Ok(Some(flattened.as_value()))
+        })?;
+        let flattened = flattened
+            .and_then(RArray::from_value)
+            .unwrap_or_else(|| ruby.ary_new());
+
+        let mut entries = ArrayEnumerator::new(ruby, flattened);
+
+        if let Some(position) = resumption {
+            entries.skip_to(position * 2);
+        }
+
         Ok(HashDeserializer {
             ruby,
-            hash,
-            keys: ArrayEnumerator::new(ruby, hash.funcall("keys", ())?).peekable(),
+            flattened,
+            entries,
+            key: None,
+            resumption,
+            context,
+        })
+    }
+
+    /// Deserializes the entries with `visitor`. When collecting errors, a missing field whose
+    /// entry was skipped has already been reported.
+    pub fn visit<Visitor>(mut self, visitor: Visitor) -> Result<Visitor::Value, Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        let output = visitor.visit_map(&mut self);
+
+        output.map_err(|error| match (error.kind(), error.expected()) {
+            (ErrorKind::MissingField, Some(field))
+                if error.path().is_empty() && self.skipped(field) =>
+            {
+                error.consequential()
+            }
+            _ => error,
         })
     }
+
+    /// Returns whether an entry with the key `name` was skipped.
+    fn skipped(&self, name: &str) -> bool {
+        let resumption = match self.resumption {
+            Some(resumption) => resumption,
+            None => return false,
+        };
+
+        (0..resumption).any(|position| {
+            self.flattened
+                .entry::<Value>(position as isize * 2)
+                .map_or(false, |key| {
+                    PathSegment::key(key) == PathSegment::Key(name.to_owned())
+                })
+        })
+    }
+}
+
+/// Collects a hash's keys and values into a flat array, `[key, value, key, value, ...]`, in a
+/// single pass over the hash. Compared to fetching `hash.keys` and then looking up each key's
+/// value, this avoids hashing every key a second time.
+///
+/// The entries are kept in a Ruby array rather than a `Vec` so that the garbage collector can
+/// see them: if Ruby code modifies the hash mid-deserialization, or the garbage collector
+/// compacts the heap, a `Vec` could be left holding freed or moved objects.
+pub fn entries(
+    ruby: &Ruby,
+    hash: RHash,
+    duplicate_keys: Option<DuplicateKeys>,
+) -> Result<RArray, Error> {
+    let entries = ruby.ary_new_capa(hash.len() * 2);
+
+    hash.foreach(|key: Value, value: Value| {
+        entries.push(key)?;
+        entries.push(value)?;
+        Ok(ForEach::Continue)
+    })?;
+
+    match duplicate_keys {
+        Some(duplicate_keys) => resolve_duplicates(ruby, entries, duplicate_keys),
+        None => Ok(entries),
+    }
 }
 
-impl<'r, 'i> MapAccess<'i> for HashDeserializer<'r> {
+/// Drops the entry of either the Symbol key or the String key wherever a hash has both for the
+/// same name, as the policy calls for. Symbols whose names aren't valid UTF-8 are left alone.
+fn resolve_duplicates(
+    ruby: &Ruby,
+    entries: RArray,
+    duplicate_keys: DuplicateKeys,
+) -> Result<RArray, Error> {
+    let mut symbol_indexes = Vec::new();
+    let mut string_indexes = HashMap::new();
+
+    for index in (0..entries.len()).step_by(2) {
+        let key: Value = entries.entry(index as isize)?;
+
+        if Symbol::from_value(key).is_some() {
+            symbol_indexes.push(index);
+        } else if let Some(string) = RString::from_value(key) {
+            string_indexes
+                .entry(unsafe { string.as_slice() }.to_vec())
+                .or_insert(index);
+        }
+    }
+
+    if symbol_indexes.is_empty() || string_indexes.is_empty() {
+        return Ok(entries);
+    }
+
+    let mut dropped = HashSet::new();
+
+    for symbol_index in symbol_indexes {
+        let symbol: Symbol = entries.entry(symbol_index as isize)?;
+        let name = match symbol_name(symbol).to_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+
+        let string_index = match string_indexes.get(name.as_bytes()) {
+            Some(&string_index) => string_index,
+            None => continue,
+        };
+
+        dropped.insert(match duplicate_keys {
+            DuplicateKeys::Error => {
+                let string = entries.entry(string_index as isize)?;
+                return Err(Error::duplicate_key(symbol.as_value(), string, &name));
+            }
+
+            DuplicateKeys::PreferSymbol => string_index,
+            DuplicateKeys::PreferString => symbol_index,
+            DuplicateKeys::LastWins => symbol_index.min(string_index),
+        });
+    }
+
+    if dropped.is_empty() {
+        return Ok(entries);
+    }
+
+    let resolved = ruby.ary_new_capa(entries.len() - dropped.len() * 2);
+
+    for index in (0..entries.len()).step_by(2) {
+        if !dropped.contains(&index) {
+            resolved.push(entries.entry::<Value>(index as isize)?)?;
+            resolved.push(entries.entry::<Value>(index as isize + 1)?)?;
+        }
+    }
+
+    Ok(resolved)
+}
+
+impl<'r, 'i> MapAccess<'i> for HashDeserializer<'r, 'i> {
     type Error = Error;
 
     fn next_key_seed<Seed>(&mut self, seed: Seed) -> Result<Option<Seed::Value>, Self::Error>
     where
         Seed: DeserializeSeed<'i>,
     {
-        match self.keys.peek() {
-            Some(&Ok(key)) => seed
-                .deserialize(Deserializer::new(self.ruby, key))
-                .map(Some),
-
-            Some(Err(error)) => Err(Error::new(
-                self.ruby.exception_runtime_error(),
-                format!("encountered unexpected error: {}", error),
-            )),
+        self.context.check_interrupts(self.ruby)?;
+
+        // The entries alternate between keys and values.
+        let position = self.entries.index() / 2;
+
+        match self.entries.next() {
+            Some(Ok(key)) => {
+                self.key = Some((position, key));
+                self.context.reenter(false);
+
+                seed.deserialize(Deserializer::new(self.ruby, key, self.context))
+                    .map(Some)
+                    .map_err(|error| self.context.fail_key(position, error))
+            }
 
+            Some(Err(error)) => Err(error.into()),
             None => Ok(None),
         }
     }
@@ -45,13 +216,27 @@ impl<'r, 'i> MapAccess<'i> for HashDeserializer<'r> {
     where
         Seed: DeserializeSeed<'i>,
     {
-        match self.keys.next() {
-            Some(Ok(key)) => seed.deserialize(Deserializer::new(self.ruby, self.hash.aref(key)?)),
-            Some(Err(error)) => Err(error.into()),
-            None => Err(Error::new(
+        match (self.key, self.entries.next()) {
+            (Some((position, key)), Some(Ok(value))) => {
+                self.context.reenter(self.resumption == Some(position));
+
+                let output = {
+                    let _descent = self.context.descend(|| PathSegment::key(key));
+                    seed.deserialize(Deserializer::new(self.ruby, value, self.context))
+                };
+
+                output.map_err(|error| self.context.fail(position, error.at(PathSegment::key(key))))
+            }
+            (_, Some(Err(error))) => Err(error.into()),
+            _ => Err(Error::new(
                 self.ruby.exception_index_error(),
                 "index out of range",
             )),
         }
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        // The entries alternate between keys and values.
+        Some(self.entries.remaining() / 2)
+    }
 }
@@ -2,18 +2,32 @@ mod deserializer;
 
 mod array_deserializer;
 mod array_enumerator;
+mod borrowed;
+mod context;
 mod enum_deserializer;
 mod hash_deserializer;
+mod options;
+mod pins;
+mod struct_deserializer;
 mod variant_deserializer;
 
+pub use self::{
+    borrowed::Borrowed,
+    options::{DeserializeOptions, DuplicateKeys},
+};
+
+pub(crate) use self::{context::Context, hash_deserializer::entries};
+
 use self::deserializer::Deserializer;
 
 use self::{
     array_deserializer::ArrayDeserializer, enum_deserializer::EnumDeserializer,
-    hash_deserializer::HashDeserializer, variant_deserializer::VariantDeserializer,
+    hash_deserializer::HashDeserializer, struct_deserializer::StructDeserializer,
+    variant_deserializer::VariantDeserializer,
 };
 
-use magnus::{Error, IntoValue, Ruby};
+use crate::error::{Error, Errors, PathSegment};
+use magnus::{error::IntoError, IntoValue, Ruby};
 use serde::Deserialize;
 
 /// Deserialize a Ruby [`Value`][`magnus::Value`] to Rust.
@@ -382,6 +396,25 @@ use serde::Deserialize;
 /// # Ok::<(), magnus::Error>(())
 /// ```
 ///
+/// With [`DeserializeOptions::enumerate`], other objects that respond to `each`, such as Sets,
+/// Ranges and Enumerators, are deserialized from the values they yield, up to the limit set by
+/// [`DeserializeOptions::max_enumerated`]. Their `each` methods run as usual, side effects and
+/// all.
+///
+/// ```
+/// # use magnus::{eval, Value};
+/// # use serde_magnus::{deserialize_with, DeserializeOptions};
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// let input: Value = eval!(&ruby, "(1..3).each_slice(2)")?;
+/// let options = DeserializeOptions::new().enumerate(true);
+/// let output: Vec<Vec<u64>> = deserialize_with(&ruby, input, &options)?;
+/// assert_eq!(vec![vec![1, 2], vec![3]], output);
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+///
 /// #### Maps
 ///
 /// ```
@@ -407,14 +440,21 @@ use serde::Deserialize;
 /// # Ok::<(), magnus::Error>(())
 /// ```
 ///
+/// ### Nesting
+///
+/// Arrays and hashes are deserialized however deeply they're nested, so deeply nested or
+/// self-referential input can overflow the stack. When deserializing untrusted input, limit the
+/// depth with [`DeserializeOptions::max_depth`] and deserialize with [`deserialize_with`].
+///
 /// ### Borrows
 ///
 /// Although [`serde::Deserialize`] is implemented for `&str`, `&[u8]`, and `&std::path::Path`, it
-/// is not possible to deserialize to these types from Ruby values. Any attempt to do so will
-/// result in an `Err`. The reason is that it cannot be guaranteed the underlying Ruby data will
-/// outlive the borrow.
+/// is not possible to deserialize to these types from Ruby values with this function. Any attempt
+/// to do so will result in an `Err`. The reason is that it cannot be guaranteed the underlying
+/// Ruby data will outlive the borrow.
 ///
-/// Use owned equivalents such as `String`, `Vec<u8>`, and [`std::path::PathBuf`] instead.
+/// Use owned equivalents such as `String`, `Vec<u8>`, and [`std::path::PathBuf`] instead, or
+/// borrow within the scope of [`deserialize_borrowed`].
 ///
 /// #### Strings
 ///
@@ -489,10 +529,430 @@ use serde::Deserialize;
 /// #
 /// # Ok::<(), magnus::Error>(())
 /// ```
-pub fn deserialize<'i, Input, Output>(ruby: &Ruby, input: Input) -> Result<Output, Error>
+pub fn deserialize<'i, Input, Output>(ruby: &Ruby, input: Input) -> Result<Output, magnus::Error>
+where
+    Input: IntoValue,
+    Output: Deserialize<'i>,
+{
+    deserialize_with(ruby, input, &DeserializeOptions::default())
+}
+
+/// Deserialize a Ruby [`Value`][`magnus::Value`] to Rust with the given [`DeserializeOptions`].
+///
+/// Conversions are as described for [`deserialize`].
+///
+/// ```
+/// # use magnus::{eval, RArray};
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// use serde_magnus::{deserialize_with, DeserializeOptions};
+///
+/// let options = DeserializeOptions::new().max_depth(2).detect_cycles(true);
+///
+/// let input: RArray = eval!(&ruby, "[[1, 2], [3]]")?;
+/// let output: Vec<Vec<u64>> = deserialize_with(&ruby, input, &options)?;
+/// assert_eq!(vec![vec![1, 2], vec![3]], output);
+///
+/// let input: RArray = eval!(&ruby, "[[[1, 2]], [3]]")?;
+/// let output: Result<Vec<Vec<Vec<u64>>>, _> = deserialize_with(&ruby, input, &options);
+/// assert_eq!(
+///     "ArgumentError: nesting of 3 is too deep",
+///     output.unwrap_err().to_string()
+/// );
+///
+/// let input: RArray = eval!(&ruby, "a = []; a << a")?;
+/// let output: Result<Vec<Vec<()>>, _> = deserialize_with(&ruby, input, &options);
+/// assert_eq!(
+///     "ArgumentError: can't deserialize recursive Array",
+///     output.unwrap_err().to_string()
+/// );
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+pub fn deserialize_with<'i, Input, Output>(
+    ruby: &Ruby,
+    input: Input,
+    options: &DeserializeOptions,
+) -> Result<Output, magnus::Error>
+where
+    Input: IntoValue,
+    Output: Deserialize<'i>,
+{
+    deserialize_detailed(ruby, input, options).map_err(|error| error.into_error(ruby))
+}
+
+/// Deserialize a Ruby [`Value`][`magnus::Value`] to Rust with the given [`DeserializeOptions`],
+/// returning an [`Error`] that can be inspected.
+///
+/// Conversions are as described for [`deserialize`]. Unlike [`deserialize_with`], which returns
+/// a [`magnus::Error`] ready to be raised in Ruby, this describes what went wrong with
+/// [`Error::kind`], [`Error::path`] and the other accessors. The error still converts to a
+/// [`magnus::Error`] with `?`.
+///
+/// ```
+/// # use magnus::{eval, RHash};
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// use serde::Deserialize;
+/// use serde_magnus::{deserialize_detailed, DeserializeOptions, ErrorKind, PathSegment};
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Post {
+///     title: String,
+///     tags: Vec<String>,
+/// }
+///
+/// let input: RHash = eval!(&ruby, "{ title: 'Update', tags: ['update', 1234] }")?;
+/// let error = deserialize_detailed::<_, Post>(&ruby, input, &DeserializeOptions::default())
+///     .unwrap_err();
+///
+/// assert_eq!(ErrorKind::InvalidType, error.kind());
+/// assert_eq!(
+///     &[PathSegment::Key("tags".into()), PathSegment::Index(1)],
+///     error.path()
+/// );
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+pub fn deserialize_detailed<'i, Input, Output>(
+    ruby: &Ruby,
+    input: Input,
+    options: &DeserializeOptions,
+) -> Result<Output, Error>
+where
+    Input: IntoValue,
+    Output: Deserialize<'i>,
+{
+    let context = Context::new(None, options.clone());
+    Output::deserialize(Deserializer::new(
+        ruby,
+        input.into_value_with(ruby),
+        &context,
+    ))
+}
+
+/// Deserialize a Ruby [`Value`][`magnus::Value`] to Rust, reporting every invalid value rather
+/// than only the first.
+///
+/// On finding an invalid element of an array or value in a hash, deserialization starts over
+/// with it skipped, as if it were absent from the input. Skipped struct fields are filled in by
+/// their defaults where they have them, such as `None` for `Option` fields and
+/// `#[serde(default)]` fields. Where a struct can't do without a skipped field, it's skipped in
+/// turn. So is a tuple or array of fixed length with an invalid element, as its elements can't
+/// be skipped. This repeats until the input deserializes or nothing more can be skipped, and
+/// then the errors are returned, each with the [path][`Error::path`] to the value it's about.
+///
+/// Each attempt resumes where the last one failed. The elements and entries before that point,
+/// which have already been deserialized, are skipped rather than deserialized again, and other
+/// objects converted with `to_hash`, `to_ary` or `each` are only converted once.
+///
+/// Exceptions raised by Ruby, such as by a method called to convert an object, stop
+/// deserialization immediately. Input exceeding the limits in `options` is skipped like any
+/// other invalid value.
+///
+/// ```
+/// # use magnus::{eval, RHash};
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// use serde::Deserialize;
+/// use serde_magnus::{deserialize_collecting, DeserializeOptions, PathSegment};
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Signup {
+///     email: String,
+///     age: u8,
+///     referrer: Option<String>,
+/// }
+///
+/// let input: RHash = eval!(&ruby, "{ email: 42, age: 300, referrer: 'friend' }")?;
+/// let errors = deserialize_collecting::<_, Signup>(&ruby, input, &DeserializeOptions::new())
+///     .unwrap_err();
+///
+/// let paths: Vec<_> = errors.iter().map(|error| error.path()).collect();
+/// assert_eq!(
+///     vec![
+///         &[PathSegment::Key("email".into())],
+///         &[PathSegment::Key("age".into())],
+///     ],
+///     paths
+/// );
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+pub fn deserialize_collecting<'i, Input, Output>(
+    ruby: &Ruby,
+    input: Input,
+    options: &DeserializeOptions,
+) -> Result<Output, Errors>
+where
+    Input: IntoValue,
+    Output: Deserialize<'i>,
+{
+    let input = input.into_value_with(ruby);
+    let context = Context::new(None, options.clone()).collecting();
+    let mut errors = Vec::new();
+
+    loop {
+        context.restart();
+
+        let error = match Output::deserialize(Deserializer::new(ruby, input, &context)) {
+            Ok(output) if errors.is_empty() => return Ok(output),
+            Ok(_) => return Err(Errors::new(errors)),
+            Err(error) => error,
+        };
+
+        let skipped = error.is_skipped();
+
+        if !error.is_consequential() || errors.is_empty() {
+            errors.push(error);
+        }
+
+        if !skipped {
+            return Err(Errors::new(errors));
+        }
+    }
+}
+
+/// Deserialize a Ruby [`Value`][`magnus::Value`] to Rust, also returning the paths to any values
+/// the output ignored, such as those of hash keys that aren't fields of a struct.
+///
+/// This makes it possible to warn about misspelled keys without rejecting them outright with
+/// `#[serde(deny_unknown_fields)]`.
+///
+/// ```
+/// # use magnus::{eval, RHash};
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// use serde::Deserialize;
+/// use serde_magnus::{deserialize_reporting_ignored, DeserializeOptions, PathSegment};
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Signup {
+///     name: String,
+///     email_address: Option<String>,
+/// }
+///
+/// let input: RHash = eval!(
+///     &ruby,
+///     "{ name: 'Alice', emial_address: 'alice@example.com' }"
+/// )?;
+/// let (output, ignored) =
+///     deserialize_reporting_ignored::<_, Signup>(&ruby, input, &DeserializeOptions::new())?;
+///
+/// assert_eq!(None, output.email_address);
+/// assert_eq!(vec![vec![PathSegment::Key("emial_address".into())]], ignored);
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+pub fn deserialize_reporting_ignored<'i, Input, Output>(
+    ruby: &Ruby,
+    input: Input,
+    options: &DeserializeOptions,
+) -> Result<(Output, Vec<Vec<PathSegment>>), magnus::Error>
+where
+    Input: IntoValue,
+    Output: Deserialize<'i>,
+{
+    let context = Context::new(None, options.clone()).reporting_ignored();
+    let output = Output::deserialize(Deserializer::new(
+        ruby,
+        input.into_value_with(ruby),
+        &context,
+    ))
+    .map_err(|error| error.into_error(ruby))?;
+
+    Ok((output, context.into_ignored()))
+}
+
+/// Deserialize a Ruby [`Value`][`magnus::Value`] into an existing Rust value, reusing its
+/// allocations where possible.
+///
+/// This is built on `Deserialize::deserialize_in_place`. For example, a `Vec` keeps its buffer
+/// and overwrites its existing elements in place, and a `String` keeps its buffer when the new
+/// contents fit. Types whose implementations don't override `deserialize_in_place` are simply
+/// replaced, as by [`deserialize`].
+///
+/// Derived implementations only override `deserialize_in_place` when `serde_derive`'s
+/// `deserialize_in_place` feature is enabled. Without it, structs are replaced whole, along with
+/// their fields' allocations. To reuse them, enable the feature in `Cargo.toml`:
+///
+/// ```toml
+/// serde_derive = { version = "1.0", features = ["deserialize_in_place"] }
+/// ```
+///
+/// ```
+/// # use magnus::{eval, RArray};
+/// # use serde_magnus::deserialize_into;
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// let mut output: Vec<String> = Vec::with_capacity(100);
+///
+/// let input: RArray = eval!(&ruby, r#"["carnival", "update"]"#)?;
+/// deserialize_into(&ruby, input, &mut output)?;
+/// assert_eq!(vec!["carnival", "update"], output);
+///
+/// let input: RArray = eval!(&ruby, r#"["spring"]"#)?;
+/// deserialize_into(&ruby, input, &mut output)?;
+/// assert_eq!(vec!["spring"], output);
+/// assert!(output.capacity() >= 100);
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+///
+/// If deserialization fails, the existing value may be left partially overwritten.
+pub fn deserialize_into<'i, Input, Output>(
+    ruby: &Ruby,
+    input: Input,
+    place: &mut Output,
+) -> Result<(), magnus::Error>
+where
+    Input: IntoValue,
+    Output: Deserialize<'i>,
+{
+    deserialize_into_with(ruby, input, place, &DeserializeOptions::default())
+}
+
+/// Deserialize a Ruby [`Value`][`magnus::Value`] into an existing Rust value with the given
+/// [`DeserializeOptions`].
+///
+/// Conversions are as described for [`deserialize_into`].
+///
+/// ```
+/// # use magnus::{eval, RArray};
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// use serde_magnus::{deserialize_into_with, DeserializeOptions};
+///
+/// let options = DeserializeOptions::new().max_array_length(2);
+/// let mut output: Vec<String> = Vec::with_capacity(100);
+///
+/// let input: RArray = eval!(&ruby, r#"["carnival", "update"]"#)?;
+/// deserialize_into_with(&ruby, input, &mut output, &options)?;
+/// assert_eq!(vec!["carnival", "update"], output);
+///
+/// let input: RArray = eval!(&ruby, r#"["spring", "carnival", "update"]"#)?;
+/// assert!(deserialize_into_with(&ruby, input, &mut output, &options).is_err());
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+pub fn deserialize_into_with<'i, Input, Output>(
+    ruby: &Ruby,
+    input: Input,
+    place: &mut Output,
+    options: &DeserializeOptions,
+) -> Result<(), magnus::Error>
 where
     Input: IntoValue,
     Output: Deserialize<'i>,
 {
-    Output::deserialize(Deserializer::new(ruby, input.into_value_with(ruby))).map_err(Into::into)
+    let context = Context::new(None, options.clone());
+    Output::deserialize_in_place(
+        Deserializer::new(ruby, input.into_value_with(ruby), &context),
+        place,
+    )
+    .map_err(|error| error.into_error(ruby))
+}
+
+/// Deserialize a Ruby [`Value`][`magnus::Value`] to Rust types that borrow from it.
+///
+/// The given function receives a [`Borrowed`] handle to the input. Types deserialized from the
+/// handle may borrow Ruby strings as `&str`, `&[u8]`, or `Cow<str>` without copying them. The
+/// borrowed strings are protected from modification, garbage collection, and compaction until
+/// the function returns.
+///
+/// ```
+/// # use magnus::{eval, RHash};
+/// # use serde_magnus::deserialize_borrowed;
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// use serde::Deserialize;
+/// use std::borrow::Cow;
+///
+/// #[derive(Deserialize)]
+/// struct Post<'a> {
+///     title: &'a str,
+///     #[serde(borrow)]
+///     tags: Vec<Cow<'a, str>>,
+/// }
+///
+/// let input: RHash = eval!(&ruby, r#"
+///   {
+///     title: "Spring carnival planning update",
+///     tags: ["carnival", "update"]
+///   }
+/// "#)?;
+///
+/// let tags = deserialize_borrowed(&ruby, input, |input| {
+///     let post: Post = input.deserialize()?;
+///     assert_eq!("Spring carnival planning update", post.title);
+///     Ok(post.tags.len())
+/// })?;
+///
+/// assert_eq!(2, tags);
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+///
+/// Strings that aren't valid UTF-8 as-is (for example, those in other encodings) are converted
+/// and copied as by [`deserialize`], so they can only be deserialized to owned types.
+pub fn deserialize_borrowed<Input, Function, Output>(
+    ruby: &Ruby,
+    input: Input,
+    function: Function,
+) -> Result<Output, magnus::Error>
+where
+    Input: IntoValue,
+    Function: FnOnce(&Borrowed) -> Result<Output, magnus::Error>,
+{
+    deserialize_borrowed_with(ruby, input, &DeserializeOptions::default(), function)
+}
+
+/// Deserialize a Ruby [`Value`][`magnus::Value`] to Rust types that borrow from it, with the
+/// given [`DeserializeOptions`].
+///
+/// Borrowing is as described for [`deserialize_borrowed`].
+///
+/// ```
+/// # use magnus::{eval, RArray};
+/// # use serde_magnus::{deserialize_borrowed_with, DeserializeOptions};
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// let options = DeserializeOptions::new().max_string_bytes(8);
+/// let input: RArray = eval!(&ruby, r#"["carnival", "spring carnival"]"#)?;
+///
+/// let output = deserialize_borrowed_with(&ruby, input, &options, |input| {
+///     let tags: Vec<&str> = input.deserialize()?;
+///     Ok(tags.len())
+/// });
+///
+/// assert_eq!(
+///     "ArgumentError: string of 15 bytes exceeds the limit of 8",
+///     output.unwrap_err().to_string()
+/// );
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+pub fn deserialize_borrowed_with<Input, Function, Output>(
+    ruby: &Ruby,
+    input: Input,
+    options: &DeserializeOptions,
+    function: Function,
+) -> Result<Output, magnus::Error>
+where
+    Input: IntoValue,
+    Function: FnOnce(&Borrowed) -> Result<Output, magnus::Error>,
+{
+    function(&Borrowed::new(
+        ruby,
+        input.into_value_with(ruby),
+        options.clone(),
+    ))
 }
@@ -0,0 +1,396 @@
+use crate::NonFiniteFloats;
+
+/// Options for [`crate::deserialize_with`] and the other functions that take them.
+///
+/// ```
+/// use serde_magnus::DeserializeOptions;
+///
+/// let options = DeserializeOptions::new().max_depth(32).detect_cycles(true);
+/// ```
+///
+/// When deserializing untrusted input, limits on its size protect against payloads crafted to
+/// exhaust memory. Input exceeding a limit raises an `ArgumentError` before it's copied.
+///
+/// ```
+/// use serde_magnus::DeserializeOptions;
+///
+/// let options = DeserializeOptions::new()
+///     .max_elements(10_000)
+///     .max_array_length(1_000)
+///     .max_hash_size(100)
+///     .max_string_bytes(64 * 1024);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DeserializeOptions {
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) detect_cycles: bool,
+    pub(crate) max_elements: Option<usize>,
+    pub(crate) max_array_length: Option<usize>,
+    pub(crate) max_hash_size: Option<usize>,
+    pub(crate) max_string_bytes: Option<usize>,
+    pub(crate) enumerate: bool,
+    pub(crate) max_enumerated: usize,
+    pub(crate) strict_numbers: bool,
+    pub(crate) coerce: bool,
+    pub(crate) empty_strings_as_none: bool,
+    pub(crate) non_finite_floats: NonFiniteFloats,
+    pub(crate) duplicate_keys: Option<DuplicateKeys>,
+    pub(crate) explicit_conversions: bool,
+}
+
+/// How to deserialize a hash with a Symbol key and a String key of the same name, such as
+/// `{ "name" => "a", name: "b" }`. See [`DeserializeOptions::duplicate_keys`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Raise an error naming both keys.
+    Error,
+    /// Keep the Symbol key's value.
+    PreferSymbol,
+    /// Keep the String key's value.
+    PreferString,
+    /// Keep the value of whichever key comes last in the hash.
+    LastWins,
+}
+
+impl Default for DeserializeOptions {
+    fn default() -> DeserializeOptions {
+        DeserializeOptions {
+            max_depth: None,
+            detect_cycles: false,
+            max_elements: None,
+            max_array_length: None,
+            max_hash_size: None,
+            max_string_bytes: None,
+            enumerate: false,
+            max_enumerated: 1_000_000,
+            strict_numbers: false,
+            coerce: false,
+            empty_strings_as_none: false,
+            non_finite_floats: NonFiniteFloats::Allow,
+            duplicate_keys: None,
+            explicit_conversions: false,
+        }
+    }
+}
+
+impl DeserializeOptions {
+    /// Returns the default options.
+    pub fn new() -> DeserializeOptions {
+        DeserializeOptions::default()
+    }
+
+    /// The maximum number of nested arrays and hashes to deserialize. Unlimited by default.
+    ///
+    /// Deserializing nested data recurses, so without a limit, deeply nested or self-referential
+    /// input could overflow the stack and crash the Ruby process. Input nested more deeply than
+    /// the limit raises an `ArgumentError` instead.
+    pub fn max_depth(mut self, max_depth: usize) -> DeserializeOptions {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Whether to track the arrays and hashes being deserialized, and raise an `ArgumentError`
+    /// on reaching one of them again. Defaults to `false`.
+    ///
+    /// Self-referential input, such as `a = []; a << a`, is otherwise only caught by
+    /// [`DeserializeOptions::max_depth`], if set, with a less specific error.
+    pub fn detect_cycles(mut self, detect_cycles: bool) -> DeserializeOptions {
+        self.detect_cycles = detect_cycles;
+        self
+    }
+
+    /// The maximum total number of array elements and hash entries in the input, counting
+    /// those of nested arrays and hashes. Unlimited by default.
+    pub fn max_elements(mut self, max_elements: usize) -> DeserializeOptions {
+        self.max_elements = Some(max_elements);
+        self
+    }
+
+    /// The maximum length of any one array in the input. Unlimited by default.
+    ///
+    /// Objects that are enumerated into a sequence, such as Enumerators, count as arrays. Their
+    /// enumeration stops once they exceed the limit, or [`DeserializeOptions::max_enumerated`]
+    /// if it's lower.
+    pub fn max_array_length(mut self, max_array_length: usize) -> DeserializeOptions {
+        self.max_array_length = Some(max_array_length);
+        self
+    }
+
+    /// The maximum number of entries in any one hash in the input. Unlimited by default.
+    pub fn max_hash_size(mut self, max_hash_size: usize) -> DeserializeOptions {
+        self.max_hash_size = Some(max_hash_size);
+        self
+    }
+
+    /// The maximum length in bytes of any one string in the input. Unlimited by default.
+    pub fn max_string_bytes(mut self, max_string_bytes: usize) -> DeserializeOptions {
+        self.max_string_bytes = Some(max_string_bytes);
+        self
+    }
+
+    /// Whether to deserialize a sequence from an object that can't be converted to an Array but
+    /// responds to `each`, such as a Set, Range or Enumerator, by enumerating the values it
+    /// yields. Defaults to `false`.
+    ///
+    /// Enumerating an object runs its `each` method, along with any side effects. An IO, for
+    /// example, is read to its end and left there. So this is only worth enabling when the input
+    /// is trusted to be enumerated, and [`DeserializeOptions::max_enumerated`] bounds how far.
+    ///
+    /// ```
+    /// # use magnus::{eval, Value};
+    /// #
+    /// # let ruby = unsafe { magnus::embed::init() };
+    /// #
+    /// use serde_magnus::{deserialize, deserialize_with, DeserializeOptions};
+    ///
+    /// let input: Value = eval!(&ruby, "(1..3).each_slice(2)")?;
+    /// assert!(deserialize::<_, Vec<Vec<u64>>>(&ruby, input).is_err());
+    ///
+    /// let options = DeserializeOptions::new().enumerate(true);
+    /// let output: Vec<Vec<u64>> = deserialize_with(&ruby, input, &options)?;
+    /// assert_eq!(vec![vec![1, 2], vec![3]], output);
+    /// #
+    /// # Ok::<(), magnus::Error>(())
+    /// ```
+    pub fn enumerate(mut self, enumerate: bool) -> DeserializeOptions {
+        self.enumerate = enumerate;
+        self
+    }
+
+    /// The maximum number of values to take from any one object that's enumerated with `each`
+    /// into a sequence, when [`DeserializeOptions::enumerate`] is set. Defaults to 1,000,000.
+    ///
+    /// Enumeration stops once an object yields more values than the limit, and an
+    /// `ArgumentError` is raised, so that endless Enumerators such as `(1..).each` raise rather
+    /// than hang.
+    ///
+    /// ```
+    /// # use magnus::{eval, Value};
+    /// #
+    /// # let ruby = unsafe { magnus::embed::init() };
+    /// #
+    /// use serde_magnus::{deserialize_with, DeserializeOptions};
+    ///
+    /// let input: Value = eval!(&ruby, "(1..).each")?;
+    /// let options = DeserializeOptions::new().enumerate(true);
+    /// assert!(deserialize_with::<_, Vec<u64>>(&ruby, input, &options).is_err());
+    ///
+    /// let input: Value = eval!(&ruby, "(1..).lazy.map { |n| n * 2 }")?;
+    /// let options = options.max_enumerated(3);
+    /// assert_eq!(
+    ///     "ArgumentError: enumeration yielded more than 3 values",
+    ///     deserialize_with::<_, Vec<u64>>(&ruby, input, &options)
+    ///         .unwrap_err()
+    ///         .to_string()
+    /// );
+    /// #
+    /// # Ok::<(), magnus::Error>(())
+    /// ```
+    pub fn max_enumerated(mut self, max_enumerated: usize) -> DeserializeOptions {
+        self.max_enumerated = max_enumerated;
+        self
+    }
+
+    /// Whether to reject numbers that would otherwise be converted, possibly with a loss of
+    /// precision. Defaults to `false`.
+    ///
+    /// In strict mode, a Ruby Float deserializes to an `f32` only if the `f32` represents it
+    /// exactly, a Ruby Integer doesn't deserialize to a float, and a Ruby Float never
+    /// deserializes to an integer. Other numbers raise an error naming the offending value.
+    ///
+    /// ```
+    /// # use magnus::{eval, Value};
+    /// #
+    /// # let ruby = unsafe { magnus::embed::init() };
+    /// #
+    /// use serde_magnus::{deserialize_detailed, DeserializeOptions};
+    ///
+    /// let options = DeserializeOptions::new().strict_numbers(true);
+    ///
+    /// let input: Value = eval!(&ruby, "0.5")?;
+    /// let output: f32 = deserialize_detailed(&ruby, input, &options)?;
+    /// assert_eq!(0.5, output);
+    ///
+    /// let input: Value = eval!(&ruby, "0.1")?;
+    /// let error = deserialize_detailed::<_, f32>(&ruby, input, &options).unwrap_err();
+    /// assert_eq!(Some("floating point `0.1`"), error.unexpected());
+    ///
+    /// let input: Value = eval!(&ruby, "1")?;
+    /// let error = deserialize_detailed::<_, f64>(&ruby, input, &options).unwrap_err();
+    /// assert_eq!(Some("integer `1`"), error.unexpected());
+    /// #
+    /// # Ok::<(), magnus::Error>(())
+    /// ```
+    pub fn strict_numbers(mut self, strict_numbers: bool) -> DeserializeOptions {
+        self.strict_numbers = strict_numbers;
+        self
+    }
+
+    /// Whether to coerce Strings and Floats to the numbers and bools expected, as form params
+    /// are cast in Rails. Defaults to `false`.
+    ///
+    /// In coercion mode, numeric Strings such as `"42"` and `"3.5"` deserialize to integers and
+    /// floats, and Strings such as `"true"`, `"1"`, `"false"` and `"0"` to bools. Surrounding
+    /// whitespace is ignored. Floats with no fractional part deserialize to integers, unless
+    /// [`DeserializeOptions::strict_numbers`] is also set.
+    ///
+    /// Only Strings naming finite numbers are coerced to floats. Strings such as `"NaN"`, `"inf"`
+    /// and `"1e400"` are rejected whatever [`DeserializeOptions::non_finite_floats`] is set to,
+    /// since that setting is for Ruby Floats.
+    ///
+    /// ```
+    /// # use magnus::{eval, RHash};
+    /// #
+    /// # let ruby = unsafe { magnus::embed::init() };
+    /// #
+    /// use serde::Deserialize;
+    /// use serde_magnus::{deserialize_with, DeserializeOptions};
+    ///
+    /// #[derive(Deserialize, PartialEq, Debug)]
+    /// struct Params {
+    ///     page: u32,
+    ///     per: u32,
+    ///     archived: bool,
+    ///     rating: Option<f64>,
+    /// }
+    ///
+    /// let options = DeserializeOptions::new()
+    ///     .coerce(true)
+    ///     .empty_strings_as_none(true);
+    ///
+    /// let input: RHash = eval!(
+    ///     &ruby,
+    ///     "{ page: '2', per: 25.0, archived: 'false', rating: '' }"
+    /// )?;
+    /// let output: Params = deserialize_with(&ruby, input, &options)?;
+    ///
+    /// assert_eq!(
+    ///     Params {
+    ///         page: 2,
+    ///         per: 25,
+    ///         archived: false,
+    ///         rating: None,
+    ///     },
+    ///     output
+    /// );
+    /// #
+    /// # Ok::<(), magnus::Error>(())
+    /// ```
+    pub fn coerce(mut self, coerce: bool) -> DeserializeOptions {
+        self.coerce = coerce;
+        self
+    }
+
+    /// Whether to deserialize empty Strings to `None` where an [`Option`] is expected, as well
+    /// as `nil`. Defaults to `false`.
+    pub fn empty_strings_as_none(mut self, empty_strings_as_none: bool) -> DeserializeOptions {
+        self.empty_strings_as_none = empty_strings_as_none;
+        self
+    }
+
+    /// How to deserialize Floats that are NaN or infinite. Defaults to
+    /// [`NonFiniteFloats::Allow`].
+    ///
+    /// With [`NonFiniteFloats::Nil`], they deserialize as `nil` would: to `None` where an
+    /// [`Option`] is expected, and otherwise to `()`.
+    ///
+    /// ```
+    /// # use magnus::{eval, Value};
+    /// #
+    /// # let ruby = unsafe { magnus::embed::init() };
+    /// #
+    /// use serde_magnus::{deserialize_with, DeserializeOptions, NonFiniteFloats};
+    ///
+    /// let input: Value = eval!(&ruby, "[1.5, Float::NAN]")?;
+    ///
+    /// let options = DeserializeOptions::new().non_finite_floats(NonFiniteFloats::Nil);
+    /// let output: Vec<Option<f64>> = deserialize_with(&ruby, input, &options)?;
+    /// assert_eq!(vec![Some(1.5), None], output);
+    ///
+    /// let options = DeserializeOptions::new().non_finite_floats(NonFiniteFloats::Error);
+    /// let error = deserialize_with::<_, Vec<f64>>(&ruby, input, &options).unwrap_err();
+    /// assert_eq!("FloatDomainError: NaN", error.to_string());
+    /// #
+    /// # Ok::<(), magnus::Error>(())
+    /// ```
+    pub fn non_finite_floats(mut self, non_finite_floats: NonFiniteFloats) -> DeserializeOptions {
+        self.non_finite_floats = non_finite_floats;
+        self
+    }
+
+    /// How to deserialize a hash with a Symbol key and a String key of the same name, as when
+    /// params are merged with a symbolized hash.
+    ///
+    /// By default, such keys are passed on to the type being deserialized as they are: structs
+    /// reject them as duplicate fields, and maps typically keep the value of the key that comes
+    /// last.
+    ///
+    /// ```
+    /// # use magnus::{eval, RHash};
+    /// #
+    /// # let ruby = unsafe { magnus::embed::init() };
+    /// #
+    /// use serde::Deserialize;
+    /// use serde_magnus::{deserialize_with, DeserializeOptions, DuplicateKeys};
+    ///
+    /// #[derive(Deserialize)]
+    /// struct User {
+    ///     name: String,
+    /// }
+    ///
+    /// let input: RHash = eval!(&ruby, "{ 'name' => 'a', name: 'b' }")?;
+    ///
+    /// let options = DeserializeOptions::new().duplicate_keys(DuplicateKeys::PreferString);
+    /// let output: User = deserialize_with(&ruby, input, &options)?;
+    /// assert_eq!("a", output.name);
+    ///
+    /// let options = DeserializeOptions::new().duplicate_keys(DuplicateKeys::Error);
+    /// let error = deserialize_with::<_, User>(&ruby, input, &options).unwrap_err();
+    /// assert_eq!(
+    ///     r#"RuntimeError: duplicate key `name`, given as both :name and "name""#,
+    ///     error.to_string()
+    /// );
+    /// #
+    /// # Ok::<(), magnus::Error>(())
+    /// ```
+    pub fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> DeserializeOptions {
+        self.duplicate_keys = Some(duplicate_keys);
+        self
+    }
+
+    /// Whether to convert objects with their explicit conversion methods, `to_h` and `to_a`,
+    /// where a map or struct or a sequence is expected. Defaults to `false`.
+    ///
+    /// Objects other than `nil`, booleans, numbers, Strings, Symbols, Arrays, and Hashes are
+    /// always converted with their implicit conversion methods, `to_hash` and `to_ary`, where
+    /// they have them. Many more objects have explicit conversion methods, such as `OpenStruct`
+    /// and `Set`, but they may lose information in the conversion.
+    ///
+    /// ```
+    /// # use magnus::{eval, Value};
+    /// #
+    /// # let ruby = unsafe { magnus::embed::init() };
+    /// #
+    /// use serde::Deserialize;
+    /// use serde_magnus::{deserialize, deserialize_with, DeserializeOptions};
+    ///
+    /// #[derive(Deserialize, PartialEq, Debug)]
+    /// struct Point {
+    ///     x: i64,
+    ///     y: i64,
+    /// }
+    ///
+    /// let input: Value = eval!(&ruby, "Struct.new(:x, :y, keyword_init: true).new(x: 1, y: 2)")?;
+    /// assert!(deserialize::<_, Point>(&ruby, input).is_err());
+    ///
+    /// let options = DeserializeOptions::new().explicit_conversions(true);
+    /// let output: Point = deserialize_with(&ruby, input, &options)?;
+    /// assert_eq!(Point { x: 1, y: 2 }, output);
+    /// #
+    /// # Ok::<(), magnus::Error>(())
+    /// ```
+    pub fn explicit_conversions(mut self, explicit_conversions: bool) -> DeserializeOptions {
+        self.explicit_conversions = explicit_conversions;
+        self
+    }
+}
@@ -0,0 +1,31 @@
+use magnus::{value::BoxValue, RString};
+use std::cell::RefCell;
+
+/// Keeps Ruby strings alive and in place while Rust data borrows from them.
+///
+/// Each string is replaced by a frozen copy, which shares the original's buffer without copying
+/// it, so that Ruby code mutating the original can't invalidate the borrow. The copy is then
+/// registered with the garbage collector, which both protects it from collection and prevents
+/// compaction from moving it.
+#[derive(Default)]
+pub struct Pins {
+    strings: RefCell<Vec<BoxValue<RString>>>,
+}
+
+impl Pins {
+    pub fn pin(&self, string: RString) -> RString {
+        let frozen = RString::new_frozen(string);
+        self.strings.borrow_mut().push(BoxValue::new(frozen));
+        frozen
+    }
+}
+
+impl Drop for Pins {
+    fn drop(&mut self) {
+        // Ruby keeps registered addresses in a linked list, most recent first. Unregistering in
+        // reverse order finds each string at the head of the list instead of scanning for it.
+        let strings = self.strings.get_mut();
+
+        while strings.pop().is_some() {}
+    }
+}
@@ -0,0 +1,394 @@
+use super::{array_enumerator::ArrayEnumerator, context::Context, Deserializer, DuplicateKeys};
+use crate::{
+    error::{Error, ErrorKind, PathSegment},
+    strings::{interned_str, symbol_name},
+};
+use magnus::{
+    r_hash::ForEach, rb_sys::AsRawValue, value::ReprValue, IntoValue, RHash, RString, Ruby, Symbol,
+    Value,
+};
+use serde::de::{value::BorrowedStrDeserializer, DeserializeSeed, MapAccess};
+
+/// Deserializes a struct from a hash by looking up each of its fields, first by Symbol and then
+/// by String, rather than visiting every key in the hash.
+///
+/// Keys that aren't fields are only looked for when the hash has more entries than were found
+/// by field. The first such key is passed to the visitor, which rejects it if the struct denies
+/// unknown fields. If the visitor ignores its value, as derived implementations of
+/// [`serde::Deserialize`] do, the rest are skipped. Otherwise, such as for an implementation that
+/// collects unknown fields, every other key that isn't a field is passed to it, too.
+///
+/// When ignored keys are being reported, every key that isn't a field is passed to the visitor
+/// up front, so that each one it ignores is seen.
+///
+/// When collecting errors, the fields come first in the order they're declared and the unknown
+/// keys follow in the order of the hash. A field or unknown key that fails is skipped on later
+/// attempts, along with those before it.
+pub struct StructDeserializer<'r, 'i> {
+    ruby: &'r Ruby,
+    hash: RHash,
+    fields: &'static [&'static str],
+    index: usize,
+    remaining: usize,
+    duplicate: Option<(&'static str, Value)>,
+    strings_last: Option<Vec<bool>>,
+    value: Option<(Key, Value, usize)>,
+    first_unknown: Option<Value>,
+    unknown: Option<ArrayEnumerator<'r>>,
+    unknown_index: usize,
+    resumption: Option<usize>,
+    context: &'r Context<'i>,
+}
+
+/// The key of the value to be deserialized next, kept to describe where errors occur.
+#[derive(Clone, Copy)]
+enum Key {
+    Field(&'static str),
+    Unknown(Value),
+}
+
+impl Key {
+    fn segment(self) -> PathSegment {
+        match self {
+            Key::Field(field) => PathSegment::Key(field.to_owned()),
+            Key::Unknown(key) => PathSegment::key(key),
+        }
+    }
+}
+
+impl<'r, 'i> StructDeserializer<'r, 'i> {
+    pub fn new(
+        ruby: &'r Ruby,
+        hash: RHash,
+        fields: &'static [&'static str],
+        context: &'r Context<'i>,
+    ) -> Result<StructDeserializer<'r, 'i>, Error> {
+        let resumption = context.resumption();
+
+        // The hash was counted on the attempt that failed within it.
+        if resumption.is_none() {
+            context.check_hash(ruby, hash.len())?;
+        }
+
+        let mut deserializer = StructDeserializer {
+            ruby,
+            hash,
+            fields,
+            index: 0,
+            remaining: hash.len(),
+            duplicate: None,
+            strings_last: None,
+            value: None,
+            first_unknown: None,
+            unknown: None,
+            unknown_index: 0,
+            resumption,
+            context,
+        };
+
+        if let Some(position) = resumption {
+            deserializer.skip_to(position);
+        }
+
+        Ok(deserializer)
+    }
+
+    /// Deserializes the fields with `visitor`. When collecting errors, a missing field that was
+    /// skipped has already been reported.
+    pub fn visit<Visitor>(mut self, visitor: Visitor) -> Result<Visitor::Value, Error>
+    where
+        Visitor: serde::de::Visitor<'i>,
+    {
+        let output = visitor.visit_map(&mut self);
+
+        output.map_err(|error| match (error.kind(), error.expected()) {
+            (ErrorKind::MissingField, Some(field))
+                if error.path().is_empty() && self.skipped(field) =>
+            {
+                error.consequential()
+            }
+            _ => error,
+        })
+    }
+
+    /// Skips the fields and unknown keys before `position`.
+    fn skip_to(&mut self, position: usize) {
+        let skipped = position.min(self.fields.len());
+
+        let fields = self.fields;
+
+        for field in &fields[..skipped] {
+            self.lookup(self.ruby.sym_new(*field));
+            self.lookup(interned_str(self.ruby, field));
+        }
+
+        self.index = skipped;
+        self.unknown_index = position - skipped;
+    }
+
+    /// Returns whether the field `name` is in the hash and was skipped.
+    fn skipped(&self, name: &str) -> bool {
+        let resumption = match self.resumption {
+            Some(resumption) => resumption,
+            None => return false,
+        };
+
+        self.fields[..resumption.min(self.fields.len())]
+            .iter()
+            .any(|field| *field == name)
+            && (self.hash.get(self.ruby.sym_new(name)).is_some()
+                || self.hash.get(interned_str(self.ruby, name)).is_some())
+    }
+
+    fn lookup<Key>(&mut self, key: Key) -> Option<Value>
+    where
+        Key: IntoValue,
+    {
+        let value = self.hash.get(key);
+
+        if value.is_some() {
+            self.remaining -= 1;
+        }
+
+        value
+    }
+
+    fn next_field(&mut self) -> Result<Option<(&'static str, Value)>, Error> {
+        while self.remaining > 0 {
+            let field = match self.fields.get(self.index) {
+                Some(field) => *field,
+                None => return Ok(None),
+            };
+            self.index += 1;
+
+            let symbol = self.lookup(self.ruby.sym_new(field));
+            let string = if self.remaining > 0 {
+                self.lookup(interned_str(self.ruby, field))
+            } else {
+                None
+            };
+
+            match (symbol, string) {
+                (Some(symbol), Some(string)) => {
+                    let value = match self.context.options().duplicate_keys {
+                        None => {
+                            // Let the visitor report the duplicate field.
+                            self.duplicate = Some((field, string));
+                            symbol
+                        }
+
+                        Some(DuplicateKeys::Error) => {
+                            return Err(Error::duplicate_key(
+                                self.ruby.sym_new(field).as_value(),
+                                self.ruby.str_new(field).as_value(),
+                                field,
+                            ))
+                        }
+
+                        Some(DuplicateKeys::PreferSymbol) => symbol,
+                        Some(DuplicateKeys::PreferString) => string,
+
+                        Some(DuplicateKeys::LastWins) => {
+                            if self.string_is_last(self.index - 1)? {
+                                string
+                            } else {
+                                symbol
+                            }
+                        }
+                    };
+
+                    return Ok(Some((field, value)));
+                }
+
+                (Some(value), None) | (None, Some(value)) => return Ok(Some((field, value))),
+                (None, None) => {}
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns whether a field's String key comes after its Symbol key in the hash. The order of
+    /// every field's keys is worked out in one pass over the hash, the first time it's needed.
+    fn string_is_last(&mut self, index: usize) -> Result<bool, Error> {
+        if let Some(strings_last) = &self.strings_last {
+            return Ok(strings_last[index]);
+        }
+
+        let fields = self.fields;
+        let mut strings_last = vec![false; fields.len()];
+
+        self.hash.foreach(|key: Value, _: Value| {
+            if let Some(index) = field_index(key, fields) {
+                strings_last[index] = RString::from_value(key).is_some();
+            }
+
+            Ok(ForEach::Continue)
+        })?;
+
+        let string_is_last = strings_last[index];
+        self.strings_last = Some(strings_last);
+        Ok(string_is_last)
+    }
+
+    fn next_unknown(&mut self) -> Result<Option<(Value, Value)>, Error> {
+        if let Some(unknown) = &mut self.unknown {
+            return match (unknown.next().transpose()?, unknown.next().transpose()?) {
+                (Some(key), Some(value)) => Ok(Some((key, value))),
+                _ => Ok(None),
+            };
+        }
+
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        let fields = self.fields;
+
+        // Unknown keys skipped when collecting errors, which come before any already seen.
+        let mut skipped = if self.first_unknown.is_none() {
+            self.unknown_index
+        } else {
+            0
+        };
+
+        // Unless ignored keys are being reported, stop at the first unknown key to see whether
+        // the visitor ignores it.
+        if self.first_unknown.is_none() && !self.context.reports_ignored() {
+            let mut unknown = None;
+
+            self.hash.foreach(|key: Value, value: Value| {
+                if is_field(key, fields) {
+                    Ok(ForEach::Continue)
+                } else if skipped > 0 {
+                    skipped -= 1;
+                    Ok(ForEach::Continue)
+                } else {
+                    unknown = Some((key, value));
+                    Ok(ForEach::Stop)
+                }
+            })?;
+
+            match unknown {
+                Some((key, _)) => self.first_unknown = Some(key),
+                None => self.remaining = 0,
+            }
+
+            return Ok(unknown);
+        }
+
+        let first_unknown = self.first_unknown;
+        let mut passed_first = first_unknown.is_none();
+        let unknown = self.ruby.ary_new();
+
+        self.hash.foreach(|key: Value, value: Value| {
+            if !passed_first {
+                passed_first = first_unknown.map_or(false, |first| first.as_raw() == key.as_raw());
+            } else if !is_field(key, fields) {
+                if skipped > 0 {
+                    skipped -= 1;
+                } else {
+                    unknown.push(key)?;
+                    unknown.push(value)?;
+                }
+            }
+
+            Ok(ForEach::Continue)
+        })?;
+
+        self.remaining = 0;
+        self.unknown = Some(ArrayEnumerator::new(self.ruby, unknown));
+        self.next_unknown()
+    }
+}
+
+/// Returns the index of the field a Symbol or String key names, if any. Keys whose names aren't
+/// valid UTF-8 can't name a field.
+fn field_index(key: Value, fields: &[&str]) -> Option<usize> {
+    let name = match Symbol::from_value(key) {
+        Some(symbol) => symbol_name(symbol),
+        None => RString::from_value(key)?,
+    };
+
+    let name = unsafe { name.as_slice() };
+    fields.iter().position(|field| field.as_bytes() == name)
+}
+
+fn is_field(key: Value, fields: &[&str]) -> bool {
+    field_index(key, fields).is_some()
+}
+
+impl<'r, 'i> MapAccess<'i> for StructDeserializer<'r, 'i> {
+    type Error = Error;
+
+    fn next_key_seed<Seed>(&mut self, seed: Seed) -> Result<Option<Seed::Value>, Self::Error>
+    where
+        Seed: DeserializeSeed<'i>,
+    {
+        self.context.check_interrupts(self.ruby)?;
+
+        let next = match self.duplicate.take() {
+            Some(duplicate) => Some(duplicate),
+            None => self.next_field()?,
+        };
+
+        if let Some((field, value)) = next {
+            self.value = Some((Key::Field(field), value, self.index - 1));
+            return seed
+                .deserialize(BorrowedStrDeserializer::new(field))
+                .map(Some);
+        }
+
+        if let Some((key, value)) = self.next_unknown()? {
+            let position = self.fields.len() + self.unknown_index;
+            self.unknown_index += 1;
+            self.value = Some((Key::Unknown(key), value, position));
+            self.context.reenter(false);
+
+            return seed
+                .deserialize(Deserializer::new(self.ruby, key, self.context))
+                .map(Some)
+                .map_err(|error| self.context.fail_key(position, error));
+        }
+
+        Ok(None)
+    }
+
+    fn next_value_seed<Seed>(&mut self, seed: Seed) -> Result<Seed::Value, Self::Error>
+    where
+        Seed: DeserializeSeed<'i>,
+    {
+        match self.value.take() {
+            Some((key, value, position)) => {
+                // Whether the visitor ignores the first unknown key's value decides whether the
+                // rest are passed to it.
+                let first_unknown = matches!(key, Key::Unknown(_)) && self.unknown.is_none();
+
+                if first_unknown {
+                    // Forget any value ignored earlier.
+                    self.context.ignored();
+                }
+
+                self.context.reenter(self.resumption == Some(position));
+
+                let output = {
+                    let _descent = self.context.descend(|| key.segment());
+                    seed.deserialize(Deserializer::new(self.ruby, value, self.context))
+                };
+
+                let output =
+                    output.map_err(|error| self.context.fail(position, error.at(key.segment())))?;
+
+                if first_unknown && self.context.ignored() {
+                    self.remaining = 0;
+                }
+
+                Ok(output)
+            }
+            None => Err(Error::new(
+                self.ruby.exception_index_error(),
+                "index out of range",
+            )),
+        }
+    }
+}
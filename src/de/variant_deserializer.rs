@@ -1,20 +1,29 @@
-use super::{ArrayDeserializer, Deserializer, HashDeserializer};
+use super::{context::Context, ArrayDeserializer, Deserializer, StructDeserializer};
 use crate::error::Error;
 use magnus::{value::ReprValue, RArray, RHash, Ruby, Value};
 use serde::de::{DeserializeSeed, Unexpected, VariantAccess};
 
-pub struct VariantDeserializer<'r> {
+pub struct VariantDeserializer<'r, 'i> {
     ruby: &'r Ruby,
     value: Value,
+    context: &'r Context<'i>,
 }
 
-impl<'r> VariantDeserializer<'r> {
-    pub fn new(ruby: &'r Ruby, value: Value) -> VariantDeserializer<'r> {
-        VariantDeserializer { ruby, value }
+impl<'r, 'i> VariantDeserializer<'r, 'i> {
+    pub fn new(
+        ruby: &'r Ruby,
+        value: Value,
+        context: &'r Context<'i>,
+    ) -> VariantDeserializer<'r, 'i> {
+        VariantDeserializer {
+            ruby,
+            value,
+            context,
+        }
     }
 }
 
-impl<'r, 'i> VariantAccess<'i> for VariantDeserializer<'r> {
+impl<'r, 'i> VariantAccess<'i> for VariantDeserializer<'r, 'i> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<(), Self::Error> {
@@ -33,7 +42,7 @@ impl<'r, 'i> VariantAccess<'i> for VariantDeserializer<'r> {
     where
         Seed: DeserializeSeed<'i>,
     {
-        seed.deserialize(Deserializer::new(self.ruby, self.value))
+        seed.deserialize(Deserializer::new(self.ruby, self.value, self.context))
     }
 
     fn tuple_variant<Visitor>(
@@ -45,7 +54,13 @@ impl<'r, 'i> VariantAccess<'i> for VariantDeserializer<'r> {
         Visitor: serde::de::Visitor<'i>,
     {
         if let Some(array) = RArray::from_value(self.value) {
-            visitor.visit_seq(&mut ArrayDeserializer::new(self.ruby, array))
+            let _nesting = self.context.enter(self.ruby, self.value)?;
+            visitor.visit_seq(&mut ArrayDeserializer::new(
+                self.ruby,
+                array,
+                false,
+                self.context,
+            )?)
         } else {
             Err(serde::de::Error::invalid_type(
                 #[allow(clippy::unnecessary_to_owned)]
@@ -57,14 +72,15 @@ impl<'r, 'i> VariantAccess<'i> for VariantDeserializer<'r> {
 
     fn struct_variant<Visitor>(
         self,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: Visitor,
     ) -> Result<Visitor::Value, Self::Error>
     where
         Visitor: serde::de::Visitor<'i>,
     {
         if let Some(hash) = RHash::from_value(self.value) {
-            visitor.visit_map(&mut HashDeserializer::new(self.ruby, hash)?)
+            let _nesting = self.context.enter(self.ruby, self.value)?;
+            StructDeserializer::new(self.ruby, hash, fields, self.context)?.visit(visitor)
         } else {
             Err(serde::de::Error::invalid_type(
                 #[allow(clippy::unnecessary_to_owned)]
@@ -1,25 +1,266 @@
-use magnus::{ExceptionClass, Ruby};
+use magnus::{error::IntoError, value::ReprValue, ExceptionClass, RString, Ruby, Symbol, Value};
 use std::{borrow::Cow, fmt};
 
+/// An error converting between Rust and Ruby, as returned by [`crate::deserialize_detailed`],
+/// [`crate::serialize_detailed`] and [`crate::to_tree`], and collected by
+/// [`crate::deserialize_collecting`].
+///
+/// Errors convert to [`magnus::Error`] with `?`, so they can be raised in Ruby. Rust callers can
+/// instead inspect them with [`Error::kind`] and the other accessors. The functions that return
+/// [`magnus::Error`], such as [`crate::deserialize`], raise the same exceptions.
+///
+/// ```
+/// # use magnus::{eval, RHash};
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// use serde::Deserialize;
+/// use serde_magnus::{deserialize_detailed, DeserializeOptions, ErrorKind, PathSegment};
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Post {
+///     title: String,
+///     tags: Vec<String>,
+/// }
+///
+/// let input: RHash = eval!(&ruby, "{ title: 'Update', tags: ['update', 1234] }")?;
+/// let options = DeserializeOptions::default();
+/// let error = deserialize_detailed::<_, Post>(&ruby, input, &options).unwrap_err();
+///
+/// assert_eq!(ErrorKind::InvalidType, error.kind());
+/// assert_eq!(
+///     &[PathSegment::Key("tags".into()), PathSegment::Index(1)],
+///     error.path()
+/// );
+/// assert_eq!(Some("a string"), error.expected());
+/// assert_eq!(Some("integer `1234`"), error.unexpected());
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
 #[derive(Debug)]
 pub struct Error {
-    inner: magnus::Error,
+    kind: ErrorKind,
+    repr: Repr,
+    expected: Option<String>,
+    unexpected: Option<String>,
+    path: Vec<PathSegment>,
+    skipped: bool,
+    consequential: bool,
+}
+
+#[derive(Debug)]
+enum Repr {
+    /// An error raised by Ruby, or created with a known Ruby exception class, which its kind
+    /// doesn't change.
+    Ruby(magnus::Error),
+
+    /// An error reported by a [`serde::Serialize`] or [`serde::Deserialize`] implementation. It
+    /// may be created without the GVL, so its Ruby exception class is only resolved from its
+    /// kind when it's converted to a [`magnus::Error`].
+    Message(String),
+}
+
+/// The kind of an [`Error`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A value of the wrong type, such as a Ruby `String` where an integer was expected. Raised as
+    /// a `TypeError`.
+    InvalidType,
+    /// A value of the right type but an unacceptable value. Raised as a `RuntimeError`, or as a
+    /// `FloatDomainError` for a float rejected by [`crate::NonFiniteFloats::Error`].
+    InvalidValue,
+    /// A sequence or map with the wrong number of elements. Raised as a `RuntimeError`, or as a
+    /// `TypeError` for a Ruby `Hash` given for an enum that doesn't have exactly one entry.
+    InvalidLength,
+    /// A struct field missing from the input. Raised as a `RuntimeError`.
+    MissingField,
+    /// A key in the input that isn't a field of the struct being deserialized, when the struct
+    /// denies unknown fields. Raised as a `RuntimeError`.
+    UnknownField,
+    /// A Symbol key and a String key with the same name in one hash, when
+    /// [`crate::DuplicateKeys::Error`] is in effect. Raised as a `RuntimeError`.
+    DuplicateKey,
+    /// Any other error, such as one reported by a [`serde::Serialize`] or [`serde::Deserialize`]
+    /// implementation, or input exceeding a [`crate::DeserializeOptions`] limit. Raised as a
+    /// `RuntimeError`, or as the exception class chosen for it, such as an `ArgumentError` for
+    /// exceeding a limit.
+    Custom,
+    /// An exception raised by Ruby, such as by a method called during conversion. Raised as that
+    /// exception.
+    RubyException,
+}
+
+impl ErrorKind {
+    fn class_name(self) -> &'static str {
+        match self {
+            ErrorKind::InvalidType => "TypeError",
+            _ => "RuntimeError",
+        }
+    }
+
+    fn class(self, ruby: &Ruby) -> ExceptionClass {
+        match self {
+            ErrorKind::InvalidType => ruby.exception_type_error(),
+            _ => ruby.exception_runtime_error(),
+        }
+    }
+}
+
+/// A step along the path from the input's root to the value an [`Error`] is about.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// An element of an array, by index
+    Index(usize),
+    /// A value in a hash or struct, by key
+    Key(String),
+}
+
+impl PathSegment {
+    /// Describes a hash key. Strings and Symbols are described by their contents and other keys
+    /// by their `inspect` output.
+    pub(crate) fn key(key: Value) -> PathSegment {
+        if let Some(symbol) = Symbol::from_value(key) {
+            if let Ok(name) = symbol.name() {
+                return PathSegment::Key(name.into_owned());
+            }
+        }
+
+        if let Some(string) = RString::from_value(key) {
+            if let Ok(string) = string.to_string() {
+                return PathSegment::Key(string);
+            }
+        }
+
+        PathSegment::Key(key.inspect())
+    }
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            PathSegment::Index(index) => write!(formatter, "[{}]", index),
+            PathSegment::Key(key) => write!(formatter, "[{:?}]", key),
+        }
+    }
 }
 
 impl Error {
-    pub fn new<Message>(class: ExceptionClass, message: Message) -> Error
+    /// Creates an error raised as `class`, of kind [`ErrorKind::Custom`] unless classified with
+    /// [`Error::with_kind`].
+    pub(crate) fn new<Message>(class: ExceptionClass, message: Message) -> Error
     where
         Message: Into<Cow<'static, str>>,
     {
+        Error::from(magnus::Error::new(class, message)).with_kind(ErrorKind::Custom)
+    }
+
+    fn message(kind: ErrorKind, message: String) -> Error {
         Error {
-            inner: magnus::Error::new(class, message),
+            kind,
+            repr: Repr::Message(message),
+            expected: None,
+            unexpected: None,
+            path: Vec::new(),
+            skipped: false,
+            consequential: false,
         }
     }
+
+    /// Reports a Symbol key and a String key with the same name in one hash.
+    pub(crate) fn duplicate_key(symbol: Value, string: Value, name: &str) -> Error {
+        Error::message(
+            ErrorKind::DuplicateKey,
+            format!(
+                "duplicate key `{}`, given as both {} and {}",
+                name,
+                symbol.inspect(),
+                string.inspect()
+            ),
+        )
+        .with_unexpected(&name)
+    }
+
+    /// Classifies the error without changing its Ruby exception.
+    pub(crate) fn with_kind(mut self, kind: ErrorKind) -> Error {
+        self.kind = kind;
+        self
+    }
+
+    fn with_expected(mut self, expected: &dyn fmt::Display) -> Error {
+        self.expected = Some(expected.to_string());
+        self
+    }
+
+    fn with_unexpected(mut self, unexpected: &dyn fmt::Display) -> Error {
+        self.unexpected = Some(unexpected.to_string());
+        self
+    }
+
+    /// Records that the error is about a value inside the array or hash it's propagating out of.
+    pub(crate) fn at(mut self, segment: PathSegment) -> Error {
+        self.path.insert(0, segment);
+        self
+    }
+
+    /// Marks the error as being about a value that [`crate::deserialize_collecting`] will skip on
+    /// its next attempt, or one within it.
+    pub(crate) fn skipping(mut self) -> Error {
+        self.skipped = true;
+        self
+    }
+
+    /// Returns whether the value the error is about, or one within it, will be skipped.
+    pub(crate) fn is_skipped(&self) -> bool {
+        self.skipped
+    }
+
+    /// Marks the error as following from values [`crate::deserialize_collecting`] skipped, such
+    /// as a struct field that failed to deserialize and is now missing, so that it isn't reported.
+    pub(crate) fn consequential(mut self) -> Error {
+        self.consequential = true;
+        self
+    }
+
+    /// Returns whether the error follows from skipped values.
+    pub(crate) fn is_consequential(&self) -> bool {
+        self.consequential
+    }
+
+    /// Returns the kind of error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the path from the root of the input to the value the error is about, when
+    /// deserializing. Empty if the error is about the root, or when serializing.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.path
+    }
+
+    /// Returns a description of what was expected instead, when known.
+    ///
+    /// For a missing field, this is the field's name.
+    pub fn expected(&self) -> Option<&str> {
+        self.expected.as_deref()
+    }
+
+    /// Returns a description of the value that was found, when known.
+    ///
+    /// For an unknown field or a duplicate key, this is its name.
+    pub fn unexpected(&self) -> Option<&str> {
+        self.unexpected.as_deref()
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        self.inner.fmt(formatter)
+        match &self.repr {
+            Repr::Ruby(error) => error.fmt(formatter),
+            Repr::Message(message) => {
+                write!(formatter, "{}: {}", self.kind.class_name(), message)
+            }
+        }
     }
 }
 
@@ -30,10 +271,7 @@ impl serde::ser::Error for Error {
     where
         Message: fmt::Display,
     {
-        Error::new(
-            Ruby::get().unwrap().exception_runtime_error(),
-            message.to_string(),
-        )
+        Error::message(ErrorKind::Custom, message.to_string())
     }
 }
 
@@ -42,28 +280,233 @@ impl serde::de::Error for Error {
     where
         Message: fmt::Display,
     {
-        Error::new(
-            Ruby::get().unwrap().exception_runtime_error(),
-            message.to_string(),
-        )
+        Error::message(ErrorKind::Custom, message.to_string())
     }
 
     fn invalid_type(unexpected: serde::de::Unexpected, expected: &dyn serde::de::Expected) -> Self {
-        Error::new(
-            Ruby::get().unwrap().exception_type_error(),
+        Error::message(
+            ErrorKind::InvalidType,
             format!("invalid type: expected {}, got {}", expected, unexpected),
         )
+        .with_expected(&format_args!("{}", expected))
+        .with_unexpected(&unexpected)
+    }
+
+    fn invalid_value(
+        unexpected: serde::de::Unexpected,
+        expected: &dyn serde::de::Expected,
+    ) -> Self {
+        Error::message(
+            ErrorKind::InvalidValue,
+            format!("invalid value: {}, expected {}", unexpected, expected),
+        )
+        .with_expected(&format_args!("{}", expected))
+        .with_unexpected(&unexpected)
+    }
+
+    fn invalid_length(len: usize, expected: &dyn serde::de::Expected) -> Self {
+        Error::message(
+            ErrorKind::InvalidLength,
+            format!("invalid length {}, expected {}", len, expected),
+        )
+        .with_expected(&format_args!("{}", expected))
+        .with_unexpected(&len)
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self {
+        let message = if expected.is_empty() {
+            format!("unknown field `{}`, there are no fields", field)
+        } else {
+            format!("unknown field `{}`, expected {}", field, OneOf(expected))
+        };
+
+        let error = Error::message(ErrorKind::UnknownField, message).with_unexpected(&field);
+
+        if expected.is_empty() {
+            error
+        } else {
+            error.with_expected(&OneOf(expected))
+        }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error::message(
+            ErrorKind::MissingField,
+            format!("missing field `{}`", field),
+        )
+        .with_expected(&field)
+    }
+}
+
+/// Classifies an error raised by Ruby on converting part of the input, such as a String that
+/// isn't valid in its encoding, as being about the input rather than an exception.
+pub(crate) fn classify(kind: ErrorKind) -> impl FnOnce(magnus::Error) -> Error {
+    move |error| Error::from(error).with_kind(kind)
+}
+
+/// Lists alternatives as serde's default error messages do.
+struct OneOf(&'static [&'static str]);
+
+impl fmt::Display for OneOf {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.0 {
+            [] => Ok(()),
+            [only] => write!(formatter, "`{}`", only),
+            [first, second] => write!(formatter, "`{}` or `{}`", first, second),
+            alternatives => {
+                formatter.write_str("one of ")?;
+
+                for (index, alternative) in alternatives.iter().enumerate() {
+                    if index > 0 {
+                        formatter.write_str(", ")?;
+                    }
+
+                    write!(formatter, "`{}`", alternative)?;
+                }
+
+                Ok(())
+            }
+        }
     }
 }
 
 impl From<magnus::Error> for Error {
     fn from(error: magnus::Error) -> Error {
-        Error { inner: error }
+        Error {
+            kind: ErrorKind::RubyException,
+            repr: Repr::Ruby(error),
+            expected: None,
+            unexpected: None,
+            path: Vec::new(),
+            skipped: false,
+            consequential: false,
+        }
+    }
+}
+
+impl IntoError for Error {
+    fn into_error(self, ruby: &Ruby) -> magnus::Error {
+        match self.repr {
+            Repr::Ruby(error) => error,
+            Repr::Message(message) => magnus::Error::new(self.kind.class(ruby), message),
+        }
     }
 }
 
+/// Converts to a [`magnus::Error`] on a Ruby thread holding the GVL. Prefer
+/// [`IntoError::into_error`] where a [`Ruby`] handle is at hand.
+///
+/// # Panics
+///
+/// Panics if called on a thread that isn't holding the GVL, such as one spawned from Rust.
+/// Errors may be created and inspected without the GVL, but must be converted while holding it.
 impl From<Error> for magnus::Error {
     fn from(error: Error) -> magnus::Error {
-        error.inner
+        let ruby = Ruby::get().expect("converting to magnus::Error requires the GVL");
+        error.into_error(&ruby)
+    }
+}
+
+/// Every error found by [`crate::deserialize_collecting`], in the order they were found.
+///
+/// Errors convert to a single [`magnus::Error`] with `?`. More than one error is raised as a
+/// `RuntimeError` listing each of them with its path.
+#[derive(Debug)]
+pub struct Errors {
+    errors: Vec<Error>,
+}
+
+impl Errors {
+    pub(crate) fn new(errors: Vec<Error>) -> Errors {
+        Errors { errors }
+    }
+
+    /// Returns the number of errors. There is always at least one.
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Always returns `false`, as there is always at least one error.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns an iterator over the errors.
+    pub fn iter(&self) -> std::slice::Iter<'_, Error> {
+        self.errors.iter()
+    }
+
+    /// Returns the errors as a `Vec`.
+    pub fn into_vec(self) -> Vec<Error> {
+        self.errors
+    }
+}
+
+impl<'a> IntoIterator for &'a Errors {
+    type Item = &'a Error;
+    type IntoIter = std::slice::Iter<'a, Error>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for Errors {
+    type Item = Error;
+    type IntoIter = std::vec::IntoIter<Error>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+/// Lists the errors one per line, each preceded by its path unless it's about the root.
+impl fmt::Display for Errors {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for (index, error) in self.errors.iter().enumerate() {
+            if index > 0 {
+                formatter.write_str("\n")?;
+            }
+
+            if !error.path.is_empty() {
+                for segment in &error.path {
+                    write!(formatter, "{}", segment)?;
+                }
+
+                formatter.write_str(": ")?;
+            }
+
+            write!(formatter, "{}", error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Errors {}
+
+impl IntoError for Errors {
+    fn into_error(mut self, ruby: &Ruby) -> magnus::Error {
+        if self.errors.len() == 1 {
+            if let Some(error) = self.errors.pop() {
+                return error.into_error(ruby);
+            }
+        }
+
+        magnus::Error::new(ruby.exception_runtime_error(), self.to_string())
+    }
+}
+
+/// Converts to a [`magnus::Error`] on a Ruby thread holding the GVL. Prefer
+/// [`IntoError::into_error`] where a [`Ruby`] handle is at hand.
+///
+/// # Panics
+///
+/// Panics if called on a thread that isn't holding the GVL, such as one spawned from Rust.
+/// Errors may be created and inspected without the GVL, but must be converted while holding it.
+impl From<Errors> for magnus::Error {
+    fn from(errors: Errors) -> magnus::Error {
+        let ruby = Ruby::get().expect("converting to magnus::Error requires the GVL");
+        errors.into_error(&ruby)
     }
 }
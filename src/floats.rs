@@ -0,0 +1,51 @@
+use crate::error::{Error, ErrorKind};
+use magnus::Ruby;
+
+/// How to convert floats that are NaN or infinite. See
+/// [`crate::SerializeOptions::non_finite_floats`] and
+/// [`crate::DeserializeOptions::non_finite_floats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonFiniteFloats {
+    /// Convert them like any other float. The default.
+    #[default]
+    Allow,
+    /// Raise a `FloatDomainError`, as Ruby does when converting them to integers.
+    Error,
+    /// Convert them to `nil` in Ruby, or to `None` or `()` in Rust.
+    Nil,
+}
+
+impl NonFiniteFloats {
+    /// Applies the policy to a float, returning `None` if it's to be converted to `nil`.
+    pub(crate) fn apply(self, ruby: &Ruby, value: f64) -> Result<Option<f64>, Error> {
+        if value.is_finite() {
+            return Ok(Some(value));
+        }
+
+        match self {
+            NonFiniteFloats::Allow => Ok(Some(value)),
+            NonFiniteFloats::Nil => Ok(None),
+            NonFiniteFloats::Error => Err(Error::new(
+                ruby.exception_float_domain_error(),
+                describe(value),
+            )
+            .with_kind(ErrorKind::InvalidValue)),
+        }
+    }
+
+    /// Returns whether the policy converts the float to `nil`.
+    pub(crate) fn is_nil(self, value: f64) -> bool {
+        self == NonFiniteFloats::Nil && !value.is_finite()
+    }
+}
+
+/// Describes a non-finite float as Ruby's `Float#to_s` does.
+fn describe(value: f64) -> &'static str {
+    if value.is_nan() {
+        "NaN"
+    } else if value > 0.0 {
+        "Infinity"
+    } else {
+        "-Infinity"
+    }
+}
@@ -0,0 +1,94 @@
+use magnus::{
+    rb_sys::{protect, AsRawValue},
+    Error, Ruby,
+};
+use rb_sys::rb_thread_call_without_gvl;
+use std::{
+    ffi::c_void,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+/// Set when Ruby asks a thread running without the GVL to stop, as for `Thread#raise`,
+/// `Timeout` or Ctrl-C. See [`without_gvl`].
+#[derive(Default)]
+pub struct Cancellation(AtomicBool);
+
+impl Cancellation {
+    /// Returns whether the work being done without the GVL should stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Calls `function` with the GVL released, letting other Ruby threads run in the meantime.
+///
+/// `function` must not touch Ruby in any way. When an interrupt is delivered to this thread,
+/// such as by `Thread#raise`, the [`Cancellation`] passed to `function` is set, and `function`
+/// should return early. The exception raised by the interrupt is then returned as an `Err` and
+/// the result of `function` is discarded. Panics are caught and resumed once the GVL has been
+/// reacquired, even if an interrupt raised an exception in the meantime.
+pub fn without_gvl<Function, Output>(ruby: &Ruby, function: Function) -> Result<Output, Error>
+where
+    Function: FnOnce(&Cancellation) -> Output,
+{
+    struct Call<'c, Function, Output> {
+        function: Option<Function>,
+        output: Option<thread::Result<Output>>,
+        cancellation: &'c Cancellation,
+    }
+
+    unsafe extern "C" fn call<Function, Output>(data: *mut c_void) -> *mut c_void
+    where
+        Function: FnOnce(&Cancellation) -> Output,
+    {
+        let call = &mut *(data as *mut Call<'_, Function, Output>);
+
+        if let Some(function) = call.function.take() {
+            let cancellation = call.cancellation;
+            call.output = Some(panic::catch_unwind(AssertUnwindSafe(|| {
+                function(cancellation)
+            })));
+        }
+
+        ptr::null_mut()
+    }
+
+    // Called by Ruby, possibly from another thread, to ask `function` to stop.
+    unsafe extern "C" fn cancel(data: *mut c_void) {
+        let cancellation = &*(data as *const Cancellation);
+        cancellation.0.store(true, Ordering::Relaxed);
+    }
+
+    let cancellation = Cancellation::default();
+    let mut call = Call {
+        function: Some(function),
+        output: None,
+        cancellation: &cancellation,
+    };
+
+    let result = protect(|| {
+        unsafe {
+            rb_thread_call_without_gvl(
+                Some(call::<Function, Output>),
+                &mut call as *mut Call<'_, Function, Output> as *mut c_void,
+                Some(cancel),
+                &cancellation as *const Cancellation as *mut c_void,
+            );
+        }
+
+        ruby.qnil().as_raw()
+    });
+
+    match (result, call.output) {
+        (_, Some(Err(panic))) => panic::resume_unwind(panic),
+        (Err(error), _) => Err(error),
+        (Ok(_), Some(Ok(output))) => Ok(output),
+        (Ok(_), None) => Err(Error::new(
+            ruby.exception_runtime_error(),
+            "interrupted before releasing the GVL",
+        )),
+    }
+}
@@ -0,0 +1,28 @@
+use magnus::{Error, Ruby};
+use std::cell::Cell;
+
+/// How many elements are converted between checks for pending interrupts.
+const INTERVAL: u32 = 1024;
+
+/// Counts the elements converted in a single call, so that long conversions can be interrupted
+/// by `Thread#raise`, `Timeout.timeout` or a signal without the cost of checking after every
+/// element.
+#[derive(Default)]
+pub struct Interrupts {
+    count: Cell<u32>,
+}
+
+impl Interrupts {
+    /// Counts an element, and every [`INTERVAL`] elements runs Ruby's pending interrupts. Returns
+    /// the exception raised by an interrupt, such as the one passed to `Thread#raise`.
+    pub fn check(&self, ruby: &Ruby) -> Result<(), Error> {
+        let count = self.count.get().wrapping_add(1);
+        self.count.set(count);
+
+        if count % INTERVAL == 0 {
+            ruby.thread_check_ints()
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -122,7 +122,22 @@
 
 mod de;
 mod error;
+mod floats;
+mod gvl;
+mod interrupts;
 mod ser;
+mod strings;
+mod tree;
 
-pub use de::deserialize;
-pub use ser::serialize;
+pub use error::{Error, ErrorKind, Errors, PathSegment};
+pub use floats::NonFiniteFloats;
+
+pub use de::{
+    deserialize, deserialize_borrowed, deserialize_borrowed_with, deserialize_collecting,
+    deserialize_detailed, deserialize_into, deserialize_into_with, deserialize_reporting_ignored,
+    deserialize_with, Borrowed, DeserializeOptions, DuplicateKeys,
+};
+pub use ser::{serialize, serialize_detailed, serialize_with, SerializeOptions};
+pub use tree::{
+    deserialize_without_gvl, materialize, serialize_without_gvl, snapshot, to_tree, Tree,
+};
@@ -0,0 +1,88 @@
+use super::SerializeOptions;
+use crate::{interrupts::Interrupts, strings::interned_str};
+use magnus::{value::StaticSymbol, Error, IntoValue, RString, Ruby, Value};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    hash::{BuildHasherDefault, Hasher},
+};
+
+/// State shared by the serializers taking part in a single call to [`crate::serialize_with`].
+pub struct Context {
+    options: SerializeOptions,
+    symbols: RefCell<HashMap<(usize, usize), StaticSymbol, BuildHasherDefault<AddressHasher>>>,
+    interrupts: Interrupts,
+}
+
+impl Context {
+    pub fn new(options: SerializeOptions) -> Context {
+        Context {
+            options,
+            symbols: RefCell::default(),
+            interrupts: Interrupts::default(),
+        }
+    }
+
+    /// Returns the Symbol for a struct field name. Static Symbols are never garbage collected, so
+    /// each is looked up in Ruby's symbol table only once per call.
+    ///
+    /// Field names are `'static`, so a given field's name is always at the same address. The
+    /// cache is keyed by the name's address and length rather than its contents, which makes a
+    /// lookup much cheaper than hashing the name as Ruby's symbol table does. A name found at
+    /// two addresses is just cached twice.
+    pub fn symbol(&self, ruby: &Ruby, name: &'static str) -> StaticSymbol {
+        *self
+            .symbols
+            .borrow_mut()
+            .entry((name.as_ptr() as usize, name.len()))
+            .or_insert_with(|| ruby.sym_new(name))
+    }
+
+    /// Returns a Ruby `String` for a Rust string, interned if the options call for it.
+    pub fn str_new(&self, ruby: &Ruby, value: &str) -> RString {
+        if self.options.intern_strings {
+            interned_str(ruby, value)
+        } else {
+            ruby.str_new(value)
+        }
+    }
+
+    /// Returns a Ruby `Float` for a Rust float, or `nil` if the options call for it.
+    pub fn float(&self, ruby: &Ruby, value: f64) -> Result<Value, crate::error::Error> {
+        match self.options.non_finite_floats.apply(ruby, value)? {
+            Some(value) => Ok(value.into_value_with(ruby)),
+            None => Ok(ruby.qnil().into_value_with(ruby)),
+        }
+    }
+
+    /// Called once per element of a sequence or map, to let Ruby interrupt long conversions.
+    pub fn check_interrupts(&self, ruby: &Ruby) -> Result<(), Error> {
+        self.interrupts.check(ruby)
+    }
+}
+
+/// Hashes the address and length keying [`Context::symbol`]'s cache with a multiply and a
+/// rotate, as FxHash does, instead of SipHash. The keys aren't attacker-controlled, so there's no
+/// need for SipHash's resistance to collisions.
+#[derive(Default)]
+struct AddressHasher(u64);
+
+impl Hasher for AddressHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(u64::from(byte));
+        }
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.0 = (self.0.rotate_left(5) ^ value).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
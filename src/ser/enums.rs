@@ -1,11 +1,12 @@
-use crate::error::Error;
+use super::hash_new_capa;
+use crate::{error::Error, strings::interned_str};
 use magnus::{IntoValue, Ruby, Value};
 
 pub fn nest<Data>(ruby: &Ruby, variant: &'static str, data: Data) -> Result<Value, Error>
 where
     Data: IntoValue,
 {
-    let hash = ruby.hash_new();
-    hash.aset(variant, data)?;
+    let hash = hash_new_capa(ruby, 1);
+    hash.aset(interned_str(ruby, variant), data)?;
     Ok(hash.into_value_with(ruby))
 }
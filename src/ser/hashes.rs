@@ -0,0 +1,13 @@
+use magnus::{RHash, Ruby};
+
+/// Creates a hash with room for `capacity` entries, so that it isn't resized as they're inserted.
+/// Ruby only supports this from 3.2 onward.
+#[cfg(ruby_gte_3_2)]
+pub fn hash_new_capa(ruby: &Ruby, capacity: usize) -> RHash {
+    ruby.hash_new_capa(capacity)
+}
+
+#[cfg(not(ruby_gte_3_2))]
+pub fn hash_new_capa(ruby: &Ruby, _capacity: usize) -> RHash {
+    ruby.hash_new()
+}
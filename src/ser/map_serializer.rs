@@ -1,18 +1,20 @@
-use super::Serializer;
+use super::{Context, Serializer};
 use crate::error::Error;
 use magnus::{value::ReprValue, IntoValue, RHash, Ruby, Value};
 use serde::{ser::SerializeMap, Serialize};
 
 pub struct MapSerializer<'r> {
     ruby: &'r Ruby,
+    context: &'r Context,
     hash: RHash,
     key: Value,
 }
 
 impl<'r> MapSerializer<'r> {
-    pub fn new(ruby: &'r Ruby, hash: RHash) -> MapSerializer<'r> {
+    pub fn new(ruby: &'r Ruby, context: &'r Context, hash: RHash) -> MapSerializer<'r> {
         MapSerializer {
             ruby,
+            context,
             hash,
             key: ruby.qnil().as_value(),
         }
@@ -27,7 +29,7 @@ impl<'r> SerializeMap for MapSerializer<'r> {
     where
         Key: Serialize + ?Sized,
     {
-        self.key = key.serialize(Serializer::new(self.ruby))?;
+        self.key = key.serialize(Serializer::new(self.ruby, self.context))?;
         Ok(())
     }
 
@@ -35,8 +37,12 @@ impl<'r> SerializeMap for MapSerializer<'r> {
     where
         Value: Serialize + ?Sized,
     {
+        self.context.check_interrupts(self.ruby)?;
         self.hash
-            .aset(self.key, value.serialize(Serializer::new(self.ruby))?)
+            .aset(
+                self.key,
+                value.serialize(Serializer::new(self.ruby, self.context))?,
+            )
             .map_err(Into::into)
     }
 
@@ -1,7 +1,10 @@
 mod serializer;
 
+mod context;
 mod enums;
+mod hashes;
 mod map_serializer;
+mod options;
 mod seq_serializer;
 mod struct_serializer;
 mod struct_variant_serializer;
@@ -9,13 +12,16 @@ mod tuple_variant_serializer;
 
 use self::serializer::Serializer;
 
+pub use self::options::SerializeOptions;
+pub(crate) use self::{context::Context, hashes::hash_new_capa};
+
 use self::{
     map_serializer::MapSerializer, seq_serializer::SeqSerializer,
     struct_serializer::StructSerializer, struct_variant_serializer::StructVariantSerializer,
     tuple_variant_serializer::TupleVariantSerializer,
 };
 
-use magnus::{Error, Ruby, TryConvert};
+use magnus::{error::IntoError, Error, Ruby, TryConvert};
 use serde::Serialize;
 
 /// Serialize Rust data to a Ruby [`Value`][`magnus::Value`].
@@ -403,5 +409,73 @@ where
     Input: Serialize + ?Sized,
     Output: TryConvert,
 {
-    TryConvert::try_convert(input.serialize(Serializer::new(ruby))?)
+    serialize_with(ruby, input, &SerializeOptions::default())
+}
+
+/// Serialize Rust data to a Ruby [`Value`][`magnus::Value`] with the given [`SerializeOptions`].
+///
+/// Conversions are as described for [`serialize`].
+///
+/// ```
+/// # use magnus::{eval, Value};
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// use serde_magnus::{serialize_with, SerializeOptions};
+///
+/// let options = SerializeOptions::new().intern_strings(true);
+///
+/// let input = vec!["pending", "pending", "shipped"];
+/// let output: Value = serialize_with(&ruby, &input, &options)?;
+/// assert!(eval!(&ruby, "output == ['pending', 'pending', 'shipped']", output)?);
+/// assert!(eval!(&ruby, "output.all?(&:frozen?)", output)?);
+/// assert!(eval!(&ruby, "output[0].equal?(output[1])", output)?);
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+pub fn serialize_with<Input, Output>(
+    ruby: &Ruby,
+    input: &Input,
+    options: &SerializeOptions,
+) -> Result<Output, Error>
+where
+    Input: Serialize + ?Sized,
+    Output: TryConvert,
+{
+    serialize_detailed(ruby, input, options).map_err(|error| error.into_error(ruby))
+}
+
+/// Serialize Rust data to a Ruby [`Value`][`magnus::Value`] with the given [`SerializeOptions`],
+/// returning a [`crate::Error`] that can be inspected.
+///
+/// Conversions are as described for [`serialize`]. Unlike [`serialize_with`], which returns a
+/// [`magnus::Error`] ready to be raised in Ruby, this describes what went wrong with
+/// [`crate::Error::kind`] and the other accessors. The error still converts to a
+/// [`magnus::Error`] with `?`.
+///
+/// ```
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// use magnus::Value;
+/// use serde_magnus::{serialize_detailed, ErrorKind, NonFiniteFloats, SerializeOptions};
+///
+/// let options = SerializeOptions::new().non_finite_floats(NonFiniteFloats::Error);
+///
+/// let error = serialize_detailed::<_, Value>(&ruby, &f64::NAN, &options).unwrap_err();
+/// assert_eq!(ErrorKind::InvalidValue, error.kind());
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+pub fn serialize_detailed<Input, Output>(
+    ruby: &Ruby,
+    input: &Input,
+    options: &SerializeOptions,
+) -> Result<Output, crate::Error>
+where
+    Input: Serialize + ?Sized,
+    Output: TryConvert,
+{
+    let context = Context::new(options.clone());
+    let output = input.serialize(Serializer::new(ruby, &context))?;
+    TryConvert::try_convert(output).map_err(Into::into)
 }
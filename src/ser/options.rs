@@ -0,0 +1,60 @@
+use crate::NonFiniteFloats;
+
+/// Options for [`crate::serialize_with`].
+///
+/// ```
+/// use serde_magnus::SerializeOptions;
+///
+/// let options = SerializeOptions::new().intern_strings(true);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SerializeOptions {
+    pub(crate) intern_strings: bool,
+    pub(crate) non_finite_floats: NonFiniteFloats,
+}
+
+impl SerializeOptions {
+    /// Returns the default options.
+    pub fn new() -> SerializeOptions {
+        SerializeOptions::default()
+    }
+
+    /// Whether to convert Rust strings to interned, frozen Ruby `String`s. Defaults to `false`.
+    ///
+    /// Equal interned strings are the same Ruby object, so output with many repeated strings
+    /// takes less memory and creates less garbage. Because interned strings are frozen, Ruby code
+    /// can't modify them in place.
+    ///
+    /// Strings that are `'static` in Rust, such as enum variant names, are always interned.
+    pub fn intern_strings(mut self, intern_strings: bool) -> SerializeOptions {
+        self.intern_strings = intern_strings;
+        self
+    }
+
+    /// How to serialize floats that are NaN or infinite. Defaults to
+    /// [`NonFiniteFloats::Allow`].
+    ///
+    /// Ruby represents them as Floats, but many formats, such as JSON, can't, so code converting
+    /// the output to those formats may fail far from where the float came from.
+    ///
+    /// ```
+    /// # let ruby = unsafe { magnus::embed::init() };
+    /// #
+    /// use magnus::{value::ReprValue, Value};
+    /// use serde_magnus::{serialize_with, NonFiniteFloats, SerializeOptions};
+    ///
+    /// let options = SerializeOptions::new().non_finite_floats(NonFiniteFloats::Nil);
+    /// let output: Value = serialize_with(&ruby, &f64::NAN, &options)?;
+    /// assert!(output.is_nil());
+    ///
+    /// let options = SerializeOptions::new().non_finite_floats(NonFiniteFloats::Error);
+    /// let error = serialize_with::<_, Value>(&ruby, &f64::INFINITY, &options).unwrap_err();
+    /// assert_eq!("FloatDomainError: Infinity", error.to_string());
+    /// #
+    /// # Ok::<(), magnus::Error>(())
+    /// ```
+    pub fn non_finite_floats(mut self, non_finite_floats: NonFiniteFloats) -> SerializeOptions {
+        self.non_finite_floats = non_finite_floats;
+        self
+    }
+}
@@ -1,4 +1,4 @@
-use super::Serializer;
+use super::{Context, Serializer};
 use crate::error::Error;
 use magnus::{IntoValue, RArray, Ruby, Value};
 use serde::{
@@ -8,12 +8,17 @@ use serde::{
 
 pub struct SeqSerializer<'r> {
     ruby: &'r Ruby,
+    context: &'r Context,
     array: RArray,
 }
 
 impl<'r> SeqSerializer<'r> {
-    pub fn new(ruby: &'r Ruby, array: RArray) -> SeqSerializer<'r> {
-        SeqSerializer { ruby, array }
+    pub fn new(ruby: &'r Ruby, context: &'r Context, array: RArray) -> SeqSerializer<'r> {
+        SeqSerializer {
+            ruby,
+            context,
+            array,
+        }
     }
 }
 
@@ -25,8 +30,9 @@ impl<'r> SerializeSeq for SeqSerializer<'r> {
     where
         Element: Serialize + ?Sized,
     {
+        self.context.check_interrupts(self.ruby)?;
         self.array
-            .push(element.serialize(Serializer::new(self.ruby))?)
+            .push(element.serialize(Serializer::new(self.ruby, self.context))?)
             .map_err(Into::into)
     }
 
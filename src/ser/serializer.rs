@@ -2,18 +2,19 @@ use magnus::{IntoValue, Ruby, Value};
 use serde::Serialize;
 
 use super::{
-    enums::nest, MapSerializer, SeqSerializer, StructSerializer, StructVariantSerializer,
-    TupleVariantSerializer,
+    enums::nest, hash_new_capa, Context, MapSerializer, SeqSerializer, StructSerializer,
+    StructVariantSerializer, TupleVariantSerializer,
 };
-use crate::error::Error;
+use crate::{error::Error, strings::interned_str};
 
 pub struct Serializer<'r> {
     ruby: &'r Ruby,
+    context: &'r Context,
 }
 
 impl<'r> Serializer<'r> {
-    pub fn new(ruby: &'r Ruby) -> Self {
-        Self { ruby }
+    pub fn new(ruby: &'r Ruby, context: &'r Context) -> Self {
+        Self { ruby, context }
     }
 }
 
@@ -66,19 +67,22 @@ impl<'r> serde::Serializer for Serializer<'r> {
     }
 
     fn serialize_f32(self, value: f32) -> Result<Self::Ok, Self::Error> {
-        Ok(value.into_value_with(self.ruby))
+        self.context.float(self.ruby, value.into())
     }
 
     fn serialize_f64(self, value: f64) -> Result<Self::Ok, Self::Error> {
-        Ok(value.into_value_with(self.ruby))
+        self.context.float(self.ruby, value)
     }
 
     fn serialize_char(self, value: char) -> Result<Self::Ok, Self::Error> {
-        Ok(value.into_value_with(self.ruby))
+        self.serialize_str(value.encode_utf8(&mut [0; 4]))
     }
 
     fn serialize_str(self, value: &str) -> Result<Self::Ok, Self::Error> {
-        Ok(value.into_value_with(self.ruby))
+        Ok(self
+            .context
+            .str_new(self.ruby, value)
+            .into_value_with(self.ruby))
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Self::Error> {
@@ -110,7 +114,7 @@ impl<'r> serde::Serializer for Serializer<'r> {
         _index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        Ok(variant.into_value_with(self.ruby))
+        Ok(interned_str(self.ruby, variant).into_value_with(self.ruby))
     }
 
     fn serialize_newtype_struct<Value>(
@@ -140,6 +144,7 @@ impl<'r> serde::Serializer for Serializer<'r> {
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
         Ok(SeqSerializer::new(
             self.ruby,
+            self.context,
             self.ruby.ary_new_capa(len.unwrap_or(0)),
         ))
     }
@@ -165,21 +170,30 @@ impl<'r> serde::Serializer for Serializer<'r> {
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
         Ok(TupleVariantSerializer::new(
             self.ruby,
+            self.context,
             variant,
             self.ruby.ary_new_capa(len),
         ))
     }
 
-    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        Ok(MapSerializer::new(self.ruby, self.ruby.hash_new()))
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(MapSerializer::new(
+            self.ruby,
+            self.context,
+            hash_new_capa(self.ruby, len.unwrap_or(0)),
+        ))
     }
 
     fn serialize_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
-        Ok(StructSerializer::new(self.ruby, self.ruby.hash_new()))
+        Ok(StructSerializer::new(
+            self.ruby,
+            self.context,
+            hash_new_capa(self.ruby, len),
+        ))
     }
 
     fn serialize_struct_variant(
@@ -187,12 +201,13 @@ impl<'r> serde::Serializer for Serializer<'r> {
         _name: &'static str,
         _index: u32,
         variant: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
         Ok(StructVariantSerializer::new(
             self.ruby,
+            self.context,
             variant,
-            self.ruby.hash_new(),
+            hash_new_capa(self.ruby, len),
         ))
     }
 }
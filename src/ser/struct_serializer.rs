@@ -1,16 +1,21 @@
-use super::Serializer;
+use super::{Context, Serializer};
 use crate::error::Error;
 use magnus::{IntoValue, RHash, Ruby, Value};
 use serde::{ser::SerializeStruct, Serialize};
 
 pub struct StructSerializer<'r> {
     ruby: &'r Ruby,
+    context: &'r Context,
     hash: RHash,
 }
 
 impl<'r> StructSerializer<'r> {
-    pub fn new(ruby: &'r Ruby, hash: RHash) -> StructSerializer<'r> {
-        StructSerializer { ruby, hash }
+    pub fn new(ruby: &'r Ruby, context: &'r Context, hash: RHash) -> StructSerializer<'r> {
+        StructSerializer {
+            ruby,
+            context,
+            hash,
+        }
     }
 }
 
@@ -26,10 +31,11 @@ impl<'r> SerializeStruct for StructSerializer<'r> {
     where
         Value: Serialize + ?Sized,
     {
+        self.context.check_interrupts(self.ruby)?;
         self.hash
             .aset(
-                self.ruby.to_symbol(name),
-                value.serialize(Serializer::new(self.ruby))?,
+                self.context.symbol(self.ruby, name),
+                value.serialize(Serializer::new(self.ruby, self.context))?,
             )
             .map_err(Into::into)
     }
@@ -1,18 +1,25 @@
-use super::{enums::nest, Serializer};
+use super::{enums::nest, Context, Serializer};
 use crate::error::Error;
 use magnus::{RHash, Ruby, Value};
 use serde::{ser::SerializeStructVariant, Serialize};
 
 pub struct StructVariantSerializer<'r> {
     ruby: &'r Ruby,
+    context: &'r Context,
     variant: &'static str,
     hash: RHash,
 }
 
 impl<'r> StructVariantSerializer<'r> {
-    pub fn new(ruby: &'r Ruby, variant: &'static str, hash: RHash) -> StructVariantSerializer<'r> {
+    pub fn new(
+        ruby: &'r Ruby,
+        context: &'r Context,
+        variant: &'static str,
+        hash: RHash,
+    ) -> StructVariantSerializer<'r> {
         StructVariantSerializer {
             ruby,
+            context,
             variant,
             hash,
         }
@@ -31,10 +38,11 @@ impl<'r> SerializeStructVariant for StructVariantSerializer<'r> {
     where
         Value: Serialize + ?Sized,
     {
+        self.context.check_interrupts(self.ruby)?;
         self.hash
             .aset(
-                self.ruby.to_symbol(name),
-                value.serialize(Serializer::new(self.ruby))?,
+                self.context.symbol(self.ruby, name),
+                value.serialize(Serializer::new(self.ruby, self.context))?,
             )
             .map_err(Into::into)
     }
@@ -1,18 +1,25 @@
-use super::{enums::nest, Serializer};
+use super::{enums::nest, Context, Serializer};
 use crate::error::Error;
 use magnus::{RArray, Ruby, Value};
 use serde::{ser::SerializeTupleVariant, Serialize};
 
 pub struct TupleVariantSerializer<'r> {
     ruby: &'r Ruby,
+    context: &'r Context,
     variant: &'static str,
     array: RArray,
 }
 
 impl<'r> TupleVariantSerializer<'r> {
-    pub fn new(ruby: &'r Ruby, variant: &'static str, array: RArray) -> TupleVariantSerializer<'r> {
+    pub fn new(
+        ruby: &'r Ruby,
+        context: &'r Context,
+        variant: &'static str,
+        array: RArray,
+    ) -> TupleVariantSerializer<'r> {
         TupleVariantSerializer {
             ruby,
+            context,
             variant,
             array,
         }
@@ -27,8 +34,9 @@ impl<'r> SerializeTupleVariant for TupleVariantSerializer<'r> {
     where
         Field: Serialize + ?Sized,
     {
+        self.context.check_interrupts(self.ruby)?;
         self.array
-            .push(field.serialize(Serializer::new(self.ruby))?)
+            .push(field.serialize(Serializer::new(self.ruby, self.context))?)
             .map_err(Into::into)
     }
 
@@ -0,0 +1,28 @@
+use magnus::{
+    rb_sys::{AsRawValue, FromRawValue},
+    RString, Ruby, Symbol, Value,
+};
+use rb_sys::{rb_enc_interned_str, rb_sym2str, rb_utf8_encoding};
+use std::os::raw::{c_char, c_long};
+
+/// Returns the interned, frozen Ruby `String` for a Rust string. Unlike
+/// `ruby.str_new(value).to_interned_str()`, this looks up Ruby's table of interned strings
+/// directly, so a `String` is only allocated the first time a given value is interned.
+pub fn interned_str(_ruby: &Ruby, value: &str) -> RString {
+    let string = unsafe {
+        Value::from_raw(rb_enc_interned_str(
+            value.as_ptr() as *const c_char,
+            value.len() as c_long,
+            rb_utf8_encoding(),
+        ))
+    };
+
+    RString::from_value(string).expect("interned strings are Strings")
+}
+
+/// Returns a Symbol's name. The name is a frozen `String` the Symbol already holds, so unlike
+/// `symbol.name()`, this doesn't allocate, and it works for names that aren't valid UTF-8.
+pub fn symbol_name(symbol: Symbol) -> RString {
+    let name = unsafe { Value::from_raw(rb_sym2str(symbol.as_raw())) };
+    RString::from_value(name).expect("Symbol names are Strings")
+}
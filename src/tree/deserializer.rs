@@ -0,0 +1,234 @@
+use super::Tree;
+use crate::error::Error;
+use serde::{
+    de::{
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeSeed, EnumAccess, Error as _, IntoDeserializer, Unexpected, VariantAccess,
+    },
+    forward_to_deserialize_any,
+};
+use std::borrow::Cow;
+
+impl Tree {
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Tree::Nil => Unexpected::Unit,
+            Tree::Bool(value) => Unexpected::Bool(*value),
+            Tree::Integer(value) => Unexpected::Signed(*value),
+            Tree::UnsignedInteger(value) => Unexpected::Unsigned(*value),
+            Tree::Float(value) => Unexpected::Float(*value),
+            Tree::String(value) | Tree::InternedString(value) => Unexpected::Str(value),
+            Tree::Bytes(value) => Unexpected::Bytes(value),
+            Tree::Symbol(_) => Unexpected::Other("Symbol"),
+            Tree::Array(_) => Unexpected::Seq,
+            Tree::Hash(_) => Unexpected::Map,
+        }
+    }
+}
+
+fn visit_cow<'de, Visitor>(
+    value: Cow<'static, str>,
+    visitor: Visitor,
+) -> Result<Visitor::Value, Error>
+where
+    Visitor: serde::de::Visitor<'de>,
+{
+    match value {
+        Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
+        Cow::Owned(value) => visitor.visit_string(value),
+    }
+}
+
+/// Deserializes Rust data from a tree as [`crate::deserialize`] would from the equivalent Ruby
+/// value, without calling into Ruby.
+impl<'de> serde::Deserializer<'de> for Tree {
+    type Error = Error;
+
+    fn deserialize_any<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'de>,
+    {
+        match self {
+            Tree::Nil => visitor.visit_unit(),
+            Tree::Bool(value) => visitor.visit_bool(value),
+            Tree::Integer(value) => visitor.visit_i64(value),
+            Tree::UnsignedInteger(value) => visitor.visit_u64(value),
+            Tree::Float(value) => visitor.visit_f64(value),
+            Tree::String(value) | Tree::InternedString(value) | Tree::Symbol(value) => {
+                visit_cow(value, visitor)
+            }
+            Tree::Bytes(value) => visitor.visit_byte_buf(value),
+            Tree::Array(elements) => visitor.visit_seq(SeqDeserializer::new(elements.into_iter())),
+            Tree::Hash(entries) => visitor.visit_map(MapDeserializer::new(entries.into_iter())),
+        }
+    }
+
+    fn deserialize_bytes<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'de>,
+    {
+        match self {
+            Tree::String(value) | Tree::InternedString(value) => {
+                visitor.visit_byte_buf(value.into_owned().into_bytes())
+            }
+            Tree::Bytes(value) => visitor.visit_byte_buf(value),
+            other => Err(Error::invalid_type(other.unexpected(), &"a String")),
+        }
+    }
+
+    fn deserialize_option<Visitor>(self, visitor: Visitor) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'de>,
+    {
+        match self {
+            Tree::Nil => visitor.visit_none(),
+            other => visitor.visit_some(other),
+        }
+    }
+
+    fn deserialize_enum<Visitor>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: Visitor,
+    ) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'de>,
+    {
+        match self {
+            Tree::String(variant) | Tree::InternedString(variant) => {
+                visitor.visit_enum(EnumDeserializer {
+                    variant,
+                    value: Tree::Nil,
+                })
+            }
+
+            Tree::Hash(mut entries) if entries.len() == 1 => match entries.pop() {
+                Some((Tree::String(variant), value))
+                | Some((Tree::InternedString(variant), value)) => {
+                    visitor.visit_enum(EnumDeserializer { variant, value })
+                }
+
+                Some((key, _)) => Err(Error::invalid_type(key.unexpected(), &"a String")),
+                None => unreachable!(),
+            },
+
+            Tree::Hash(entries) => Err(Error::invalid_length(
+                entries.len(),
+                &"a Hash with one entry",
+            )),
+
+            other => Err(Error::invalid_type(other.unexpected(), &"an enum")),
+        }
+    }
+
+    fn deserialize_newtype_struct<Visitor>(
+        self,
+        _name: &'static str,
+        visitor: Visitor,
+    ) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_ignored_any<Visitor>(
+        self,
+        visitor: Visitor,
+    ) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        <Visitor: Visitor<'de>>
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Tree {
+    type Deserializer = Tree;
+
+    fn into_deserializer(self) -> Tree {
+        self
+    }
+}
+
+struct EnumDeserializer {
+    variant: Cow<'static, str>,
+    value: Tree,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Variant = VariantDeserializer;
+    type Error = Error;
+
+    fn variant_seed<Seed>(self, seed: Seed) -> Result<(Seed::Value, Self::Variant), Error>
+    where
+        Seed: DeserializeSeed<'de>,
+    {
+        seed.deserialize(Tree::String(self.variant))
+            .map(|variant| (variant, VariantDeserializer { value: self.value }))
+    }
+}
+
+struct VariantDeserializer {
+    value: Tree,
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.value {
+            Tree::Nil => Ok(()),
+            other => Err(Error::invalid_type(other.unexpected(), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<Seed>(self, seed: Seed) -> Result<Seed::Value, Self::Error>
+    where
+        Seed: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.value)
+    }
+
+    fn tuple_variant<Visitor>(
+        self,
+        _len: usize,
+        visitor: Visitor,
+    ) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'de>,
+    {
+        match self.value {
+            Tree::Array(elements) => visitor.visit_seq(SeqDeserializer::new(elements.into_iter())),
+            other => Err(Error::invalid_type(other.unexpected(), &"tuple variant")),
+        }
+    }
+
+    fn struct_variant<Visitor>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: Visitor,
+    ) -> Result<Visitor::Value, Self::Error>
+    where
+        Visitor: serde::de::Visitor<'de>,
+    {
+        match self.value {
+            Tree::Hash(entries) => visitor.visit_map(MapDeserializer::new(entries.into_iter())),
+            other => Err(Error::invalid_type(other.unexpected(), &"struct variant")),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use super::{Serializer, Tree};
+use crate::error::Error;
+use serde::{
+    ser::{SerializeMap, SerializeStruct, SerializeStructVariant},
+    Serialize,
+};
+use std::borrow::Cow;
+
+pub struct MapSerializer<'c> {
+    serializer: Serializer<'c>,
+    variant: Option<&'static str>,
+    entries: Vec<(Tree, Tree)>,
+    key: Tree,
+}
+
+impl<'c> MapSerializer<'c> {
+    pub fn new(
+        serializer: Serializer<'c>,
+        variant: Option<&'static str>,
+        len: usize,
+    ) -> MapSerializer<'c> {
+        MapSerializer {
+            serializer,
+            variant,
+            entries: Vec::with_capacity(len),
+            key: Tree::Nil,
+        }
+    }
+}
+
+impl<'c> SerializeMap for MapSerializer<'c> {
+    type Ok = Tree;
+    type Error = Error;
+
+    fn serialize_key<Key>(&mut self, key: &Key) -> Result<(), Self::Error>
+    where
+        Key: Serialize + ?Sized,
+    {
+        self.serializer.check_cancelled()?;
+        self.key = key.serialize(self.serializer)?;
+        Ok(())
+    }
+
+    fn serialize_value<Value>(&mut self, value: &Value) -> Result<(), Self::Error>
+    where
+        Value: Serialize + ?Sized,
+    {
+        let key = std::mem::replace(&mut self.key, Tree::Nil);
+        self.entries.push((key, value.serialize(self.serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        let hash = Tree::Hash(self.entries);
+
+        Ok(match self.variant {
+            Some(variant) => Tree::nest(variant, hash),
+            None => hash,
+        })
+    }
+}
+
+impl<'c> SerializeStruct for MapSerializer<'c> {
+    type Ok = Tree;
+    type Error = Error;
+
+    fn serialize_field<Value>(
+        &mut self,
+        name: &'static str,
+        value: &Value,
+    ) -> Result<(), Self::Error>
+    where
+        Value: Serialize + ?Sized,
+    {
+        self.serializer.check_cancelled()?;
+        self.entries.push((
+            Tree::Symbol(Cow::Borrowed(name)),
+            value.serialize(self.serializer)?,
+        ));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        <Self as SerializeMap>::end(self)
+    }
+}
+
+impl<'c> SerializeStructVariant for MapSerializer<'c> {
+    type Ok = Tree;
+    type Error = Error;
+
+    fn serialize_field<Value>(
+        &mut self,
+        name: &'static str,
+        value: &Value,
+    ) -> Result<(), Self::Error>
+    where
+        Value: Serialize + ?Sized,
+    {
+        <Self as SerializeStruct>::serialize_field(self, name, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        <Self as SerializeMap>::end(self)
+    }
+}
@@ -0,0 +1,83 @@
+use super::Tree;
+use crate::{
+    error::Error,
+    ser::{hash_new_capa, Context},
+    strings::interned_str,
+};
+use magnus::{
+    rb_sys::{AsRawValue, FromRawValue},
+    value::ReprValue,
+    IntoValue, Ruby, Value,
+};
+use rb_sys::{rb_ary_cat, rb_ary_new_from_values, VALUE};
+use std::{borrow::Cow, os::raw::c_long};
+
+/// Converts a tree to Ruby objects, as [`crate::serialize_with`] would have converted the data
+/// the tree was serialized from.
+pub fn materialize(ruby: &Ruby, context: &Context, tree: &Tree) -> Result<Value, Error> {
+    Ok(match tree {
+        Tree::Nil => ruby.qnil().as_value(),
+        Tree::Bool(value) => value.into_value_with(ruby),
+        Tree::Integer(value) => value.into_value_with(ruby),
+        Tree::UnsignedInteger(value) => value.into_value_with(ruby),
+        Tree::Float(value) => context.float(ruby, *value)?,
+        Tree::String(value) => context.str_new(ruby, value).as_value(),
+        Tree::InternedString(value) => interned_str(ruby, value).as_value(),
+        Tree::Bytes(value) => ruby.str_from_slice(value).as_value(),
+        Tree::Symbol(Cow::Borrowed(name)) => context.symbol(ruby, name).as_value(),
+        Tree::Symbol(Cow::Owned(name)) => ruby.to_symbol(name).as_value(),
+
+        Tree::Array(elements) => materialize_array(ruby, context, elements)?,
+
+        Tree::Hash(entries) => {
+            let hash = hash_new_capa(ruby, entries.len());
+
+            for (key, value) in entries {
+                context.check_interrupts(ruby)?;
+                hash.aset(
+                    materialize(ruby, context, key)?,
+                    materialize(ruby, context, value)?,
+                )?;
+            }
+
+            hash.as_value()
+        }
+    })
+}
+
+/// The number of elements [`materialize_array`] materializes before adding them to the array.
+const CHUNK_LEN: usize = 64;
+
+/// Converts the elements of an array in chunks, each materialized into a buffer on the stack and
+/// then added to the array in bulk. Ruby's garbage collector scans the stack conservatively, so
+/// it sees the elements in the buffer and keeps them alive until they're in the array.
+fn materialize_array(ruby: &Ruby, context: &Context, elements: &[Tree]) -> Result<Value, Error> {
+    let mut buffer: [VALUE; CHUNK_LEN] = [ruby.qnil().as_raw(); CHUNK_LEN];
+
+    if elements.len() <= CHUNK_LEN {
+        for (slot, element) in buffer.iter_mut().zip(elements) {
+            context.check_interrupts(ruby)?;
+            *slot = materialize(ruby, context, element)?.as_raw();
+        }
+
+        return Ok(unsafe {
+            Value::from_raw(rb_ary_new_from_values(
+                elements.len() as c_long,
+                buffer.as_ptr(),
+            ))
+        });
+    }
+
+    let array = ruby.ary_new_capa(elements.len());
+
+    for chunk in elements.chunks(CHUNK_LEN) {
+        for (slot, element) in buffer.iter_mut().zip(chunk) {
+            context.check_interrupts(ruby)?;
+            *slot = materialize(ruby, context, element)?.as_raw();
+        }
+
+        unsafe { rb_ary_cat(array.as_raw(), buffer.as_ptr(), chunk.len() as c_long) };
+    }
+
+    Ok(array.as_value())
+}
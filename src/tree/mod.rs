@@ -0,0 +1,286 @@
+mod deserializer;
+mod serializer;
+
+mod map_serializer;
+mod materialize;
+mod seq_serializer;
+mod snapshot;
+
+use self::serializer::Serializer;
+
+use self::{map_serializer::MapSerializer, seq_serializer::SeqSerializer};
+
+use crate::error::Error;
+use crate::{
+    de,
+    gvl::{without_gvl, Cancellation},
+    ser::Context,
+    DeserializeOptions, SerializeOptions,
+};
+use magnus::{error::IntoError, IntoValue, Ruby, TryConvert};
+use serde::{de::DeserializeOwned, Serialize};
+use std::borrow::Cow;
+
+/// An owned tree of Ruby-shaped data, independent of the Ruby VM.
+///
+/// A `Tree` can be built from Rust data with [`to_tree`] without holding the GVL, or even on a
+/// thread that Ruby doesn't know about. [`materialize`] then converts it to Ruby objects in a
+/// single pass. Together, they split [`crate::serialize`] into an expensive phase that doesn't
+/// block other Ruby threads and a cheap phase that does.
+///
+/// In the other direction, [`snapshot`] copies a Ruby value into a `Tree`, and the tree
+/// implements [`serde::Deserializer`] so that Rust data can be deserialized from it without
+/// holding the GVL.
+///
+/// Each variant corresponds to the Ruby value it materializes to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tree {
+    /// `nil`
+    Nil,
+    /// `true` or `false`
+    Bool(bool),
+    /// An `Integer`
+    Integer(i64),
+    /// An `Integer` too large for [`Tree::Integer`]
+    UnsignedInteger(u64),
+    /// A `Float`
+    Float(f64),
+    /// A `String` with UTF-8 encoding
+    String(Cow<'static, str>),
+    /// A frozen, interned `String` with UTF-8 encoding, such as an enum variant name
+    InternedString(Cow<'static, str>),
+    /// A `String` with ASCII-8BIT encoding
+    Bytes(Vec<u8>),
+    /// A `Symbol`
+    Symbol(Cow<'static, str>),
+    /// An `Array`
+    Array(Vec<Tree>),
+    /// A `Hash`, with entries in insertion order
+    Hash(Vec<(Tree, Tree)>),
+}
+
+impl Tree {
+    fn nest(variant: &'static str, data: Tree) -> Tree {
+        Tree::Hash(vec![(Tree::InternedString(Cow::Borrowed(variant)), data)])
+    }
+}
+
+/// Serialize Rust data to a [`Tree`], without calling into Ruby.
+///
+/// The tree has the same shape as the Ruby value [`crate::serialize`] would produce.
+///
+/// ```
+/// use serde_magnus::{to_tree, Tree};
+///
+/// let tree = to_tree(&vec![(1234, "Hello, world!")])?;
+///
+/// assert_eq!(
+///     Tree::Array(vec![Tree::Array(vec![
+///         Tree::Integer(1234),
+///         Tree::String("Hello, world!".into()),
+///     ])]),
+///     tree
+/// );
+/// #
+/// # Ok::<(), serde_magnus::Error>(())
+/// ```
+pub fn to_tree<Input>(input: &Input) -> Result<Tree, Error>
+where
+    Input: Serialize + ?Sized,
+{
+    input.serialize(Serializer::new(&Cancellation::default()))
+}
+
+/// Convert a [`Tree`] to Ruby objects.
+///
+/// ```
+/// # use magnus::{eval, Value};
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// use serde::Serialize;
+/// use serde_magnus::{materialize, to_tree, SerializeOptions};
+///
+/// #[derive(Serialize)]
+/// struct Author {
+///     name: String,
+///     email_address: String
+/// }
+///
+/// let tree = std::thread::spawn(|| {
+///     to_tree(&Author {
+///         name: "Martha".into(),
+///         email_address: "martha@example.com".into()
+///     })
+///     .unwrap()
+/// })
+/// .join()
+/// .unwrap();
+///
+/// let output: Value = materialize(&ruby, &tree, &SerializeOptions::default())?;
+/// assert!(eval!(
+///     &ruby,
+///     "output == { name: 'Martha', email_address: 'martha@example.com' }",
+///     output
+/// )?);
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+pub fn materialize<Output>(
+    ruby: &Ruby,
+    tree: &Tree,
+    options: &SerializeOptions,
+) -> Result<Output, magnus::Error>
+where
+    Output: TryConvert,
+{
+    let context = Context::new(options.clone());
+    let output =
+        materialize::materialize(ruby, &context, tree).map_err(|error| error.into_error(ruby))?;
+    TryConvert::try_convert(output)
+}
+
+/// Serialize Rust data to a Ruby [`Value`][`magnus::Value`], releasing the GVL for as much of
+/// the work as possible.
+///
+/// The input is first serialized to a [`Tree`] with the GVL released, so that other Ruby threads
+/// can run while its [`Serialize`] implementation does. The tree is then materialized with the
+/// GVL held. The result is the same as [`crate::serialize_with`]'s.
+///
+/// The input's [`Serialize`] implementation must not call into Ruby. Requiring `Sync` rules out
+/// inputs containing Ruby values, which aren't.
+///
+/// If the thread is interrupted while the tree is being built, such as by `Thread#raise` or
+/// `Timeout`, building stops at the next element of a sequence or map, and the interrupt's
+/// exception is raised.
+///
+/// ```
+/// # use magnus::{eval, Value};
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// use serde_magnus::{serialize_without_gvl, SerializeOptions};
+///
+/// let input: Vec<u64> = (1..=1000).collect();
+/// let output: Value = serialize_without_gvl(&ruby, &input, &SerializeOptions::default())?;
+/// assert!(eval!(&ruby, "output == (1..1000).to_a", output)?);
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+pub fn serialize_without_gvl<Input, Output>(
+    ruby: &Ruby,
+    input: &Input,
+    options: &SerializeOptions,
+) -> Result<Output, magnus::Error>
+where
+    Input: Serialize + Sync + ?Sized,
+    Output: TryConvert,
+{
+    let tree = without_gvl(ruby, |cancellation| {
+        input.serialize(Serializer::new(cancellation))
+    })?
+    .map_err(|error| error.into_error(ruby))?;
+    materialize(ruby, &tree, options)
+}
+
+/// Copy a Ruby [`Value`][`magnus::Value`] into a [`Tree`].
+///
+/// The tree owns all of its data, so it can be deserialized from after the GVL is released or on
+/// another thread. It accepts the same core Ruby types as [`crate::deserialize_with`], and applies
+/// the options' limits on nesting and size, cycle detection, duplicate key policy and
+/// [`non_finite_floats`][crate::DeserializeOptions::non_finite_floats] policy while copying. Under
+/// [`NonFiniteFloats::Nil`][crate::NonFiniteFloats::Nil], NaN and infinite Floats are copied as
+/// [`Tree::Nil`].
+///
+/// Deserializing from the tree can still differ from deserializing from the Ruby value:
+///
+/// - Strings with ASCII-8BIT encoding that aren't valid UTF-8 are copied as [`Tree::Bytes`], so
+///   they deserialize to bytes but not to Rust strings.
+/// - The options that depend on the Rust type being deserialized aren't applied:
+///   [`strict_numbers`][crate::DeserializeOptions::strict_numbers],
+///   [`coerce`][crate::DeserializeOptions::coerce],
+///   [`empty_strings_as_none`][crate::DeserializeOptions::empty_strings_as_none] and
+///   [`explicit_conversions`][crate::DeserializeOptions::explicit_conversions].
+/// - Other objects aren't converted with `to_hash`, `to_ary` or `each`, and are rejected instead.
+///
+/// ```
+/// # use magnus::{eval, RHash};
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// use serde::Deserialize;
+/// use serde_magnus::{snapshot, DeserializeOptions};
+///
+/// #[derive(Deserialize, PartialEq, Debug)]
+/// struct Author {
+///     name: String,
+///     email_address: String
+/// }
+///
+/// let input: RHash = eval!(&ruby, "{ name: 'Martha', email_address: 'martha@example.com' }")?;
+/// let tree = snapshot(&ruby, input, &DeserializeOptions::default())?;
+///
+/// let output = std::thread::spawn(move || Author::deserialize(tree).unwrap())
+///     .join()
+///     .unwrap();
+///
+/// assert_eq!(
+///     Author {
+///         name: "Martha".into(),
+///         email_address: "martha@example.com".into()
+///     },
+///     output
+/// );
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+pub fn snapshot<Input>(
+    ruby: &Ruby,
+    input: Input,
+    options: &DeserializeOptions,
+) -> Result<Tree, magnus::Error>
+where
+    Input: IntoValue,
+{
+    let context = de::Context::new(None, options.clone());
+    snapshot::snapshot(ruby, &context, input.into_value_with(ruby))
+        .map_err(|error| error.into_error(ruby))
+}
+
+/// Deserialize a Ruby [`Value`][`magnus::Value`] to Rust, releasing the GVL for as much of the
+/// work as possible.
+///
+/// The input is first copied with [`snapshot`] while the GVL is held. The output is then
+/// deserialized from the copy with the GVL released, so that other Ruby threads can run while
+/// its [`Deserialize`][`serde::Deserialize`] implementation does. See [`snapshot`] for how the
+/// result can differ from [`crate::deserialize_with`]'s.
+///
+/// The output's [`Deserialize`][`serde::Deserialize`] implementation must not call into Ruby.
+/// It runs to completion even if the thread is interrupted, such as by `Thread#raise`, and the
+/// interrupt is handled once it's done.
+///
+/// ```
+/// # use magnus::{eval, RArray};
+/// #
+/// # let ruby = unsafe { magnus::embed::init() };
+/// #
+/// use serde_magnus::{deserialize_without_gvl, DeserializeOptions};
+///
+/// let input: RArray = eval!(&ruby, "(1..1000).to_a")?;
+/// let output: Vec<u64> = deserialize_without_gvl(&ruby, input, &DeserializeOptions::default())?;
+/// assert_eq!((1..=1000).collect::<Vec<u64>>(), output);
+/// #
+/// # Ok::<(), magnus::Error>(())
+/// ```
+pub fn deserialize_without_gvl<Input, Output>(
+    ruby: &Ruby,
+    input: Input,
+    options: &DeserializeOptions,
+) -> Result<Output, magnus::Error>
+where
+    Input: IntoValue,
+    Output: DeserializeOwned,
+{
+    let tree = snapshot(ruby, input, options)?;
+    without_gvl(ruby, |_| Output::deserialize(tree))?.map_err(|error| error.into_error(ruby))
+}
@@ -0,0 +1,97 @@
+use super::{Serializer, Tree};
+use crate::error::Error;
+use serde::{
+    ser::{SerializeSeq, SerializeTuple, SerializeTupleStruct, SerializeTupleVariant},
+    Serialize,
+};
+
+pub struct SeqSerializer<'c> {
+    serializer: Serializer<'c>,
+    variant: Option<&'static str>,
+    elements: Vec<Tree>,
+}
+
+impl<'c> SeqSerializer<'c> {
+    pub fn new(
+        serializer: Serializer<'c>,
+        variant: Option<&'static str>,
+        len: usize,
+    ) -> SeqSerializer<'c> {
+        SeqSerializer {
+            serializer,
+            variant,
+            elements: Vec::with_capacity(len),
+        }
+    }
+}
+
+impl<'c> SerializeSeq for SeqSerializer<'c> {
+    type Ok = Tree;
+    type Error = Error;
+
+    fn serialize_element<Element>(&mut self, element: &Element) -> Result<(), Self::Error>
+    where
+        Element: Serialize + ?Sized,
+    {
+        self.serializer.check_cancelled()?;
+        self.elements.push(element.serialize(self.serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        let array = Tree::Array(self.elements);
+
+        Ok(match self.variant {
+            Some(variant) => Tree::nest(variant, array),
+            None => array,
+        })
+    }
+}
+
+impl<'c> SerializeTuple for SeqSerializer<'c> {
+    type Ok = Tree;
+    type Error = Error;
+
+    fn serialize_element<Element>(&mut self, element: &Element) -> Result<(), Self::Error>
+    where
+        Element: Serialize + ?Sized,
+    {
+        <Self as SerializeSeq>::serialize_element(self, element)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        <Self as SerializeSeq>::end(self)
+    }
+}
+
+impl<'c> SerializeTupleStruct for SeqSerializer<'c> {
+    type Ok = Tree;
+    type Error = Error;
+
+    fn serialize_field<Field>(&mut self, field: &Field) -> Result<(), Self::Error>
+    where
+        Field: Serialize + ?Sized,
+    {
+        <Self as SerializeSeq>::serialize_element(self, field)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        <Self as SerializeSeq>::end(self)
+    }
+}
+
+impl<'c> SerializeTupleVariant for SeqSerializer<'c> {
+    type Ok = Tree;
+    type Error = Error;
+
+    fn serialize_field<Field>(&mut self, field: &Field) -> Result<(), Self::Error>
+    where
+        Field: Serialize + ?Sized,
+    {
+        <Self as SerializeSeq>::serialize_element(self, field)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        <Self as SerializeSeq>::end(self)
+    }
+}
@@ -0,0 +1,198 @@
+use super::{MapSerializer, SeqSerializer, Tree};
+use crate::{error::Error, gvl::Cancellation};
+use serde::Serialize;
+use std::{borrow::Cow, convert::TryFrom};
+
+/// Serializes Rust data to a [`Tree`] with the same shape as the Ruby value [`crate::serialize`]
+/// would produce, without calling into Ruby.
+///
+/// Sequences and maps stop with an error once the cancellation is set, so that serializing
+/// without the GVL can be interrupted.
+#[derive(Clone, Copy)]
+pub struct Serializer<'c> {
+    cancellation: &'c Cancellation,
+}
+
+impl<'c> Serializer<'c> {
+    pub fn new(cancellation: &'c Cancellation) -> Serializer<'c> {
+        Serializer { cancellation }
+    }
+
+    /// Fails if the work is to stop. Called once per element of a sequence or map.
+    pub fn check_cancelled(self) -> Result<(), Error> {
+        if self.cancellation.is_cancelled() {
+            return Err(serde::ser::Error::custom("interrupted"));
+        }
+
+        Ok(())
+    }
+}
+
+impl<'c> serde::Serializer for Serializer<'c> {
+    type Ok = Tree;
+    type Error = Error;
+
+    type SerializeSeq = SeqSerializer<'c>;
+    type SerializeTuple = SeqSerializer<'c>;
+    type SerializeTupleStruct = SeqSerializer<'c>;
+    type SerializeTupleVariant = SeqSerializer<'c>;
+    type SerializeMap = MapSerializer<'c>;
+    type SerializeStruct = MapSerializer<'c>;
+    type SerializeStructVariant = MapSerializer<'c>;
+
+    fn serialize_bool(self, value: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(Tree::Bool(value))
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(Tree::Integer(value))
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<Self::Ok, Self::Error> {
+        Ok(i64::try_from(value).map_or(Tree::UnsignedInteger(value), Tree::Integer))
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(Tree::Float(value))
+    }
+
+    fn serialize_char(self, value: char) -> Result<Self::Ok, Self::Error> {
+        Ok(Tree::String(Cow::Owned(value.to_string())))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(Tree::String(Cow::Owned(value.to_owned())))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Ok(Tree::Bytes(value.to_owned()))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Tree::Nil)
+    }
+
+    fn serialize_some<Value>(self, value: &Value) -> Result<Self::Ok, Self::Error>
+    where
+        Value: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(Tree::Nil)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(Tree::Nil)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Ok(Tree::InternedString(Cow::Borrowed(variant)))
+    }
+
+    fn serialize_newtype_struct<Value>(
+        self,
+        _name: &'static str,
+        value: &Value,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        Value: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<Value>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &Value,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        Value: Serialize + ?Sized,
+    {
+        Ok(Tree::nest(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(SeqSerializer::new(self, None, len.unwrap_or(0)))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(SeqSerializer::new(self, Some(variant), len))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(MapSerializer::new(self, None, len.unwrap_or(0)))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(MapSerializer::new(self, None, len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(MapSerializer::new(self, Some(variant), len))
+    }
+}
@@ -0,0 +1,117 @@
+use super::Tree;
+use crate::{
+    de::{entries, Context},
+    error::{Error, ErrorKind},
+};
+use magnus::{
+    encoding::EncodingCapable,
+    value::{Qfalse, Qtrue, ReprValue},
+    Fixnum, Float, RArray, RBignum, RHash, RString, Ruby, Symbol, Value,
+};
+use std::borrow::Cow;
+
+/// Copies a Ruby value into a tree, accepting the same values [`crate::deserialize`] does.
+///
+/// The tree holds no references to Ruby objects, so it stays valid after the GVL is released.
+/// The options' limits on nesting and size, cycle detection, duplicate key policy and
+/// non-finite float policy are applied while copying, as they would be while deserializing.
+pub fn snapshot(ruby: &Ruby, context: &Context<'_>, value: Value) -> Result<Tree, Error> {
+    if value.is_nil() {
+        return Ok(Tree::Nil);
+    }
+
+    if let Some(qtrue) = Qtrue::from_value(value) {
+        return Ok(Tree::Bool(qtrue.to_bool()));
+    }
+
+    if let Some(qfalse) = Qfalse::from_value(value) {
+        return Ok(Tree::Bool(qfalse.to_bool()));
+    }
+
+    if let Some(fixnum) = Fixnum::from_value(value) {
+        return Ok(Tree::Integer(fixnum.to_i64()));
+    }
+
+    if let Some(bignum) = RBignum::from_value(value) {
+        return Ok(match bignum.to_i64() {
+            Ok(value) => Tree::Integer(value),
+            Err(_) => Tree::UnsignedInteger(bignum.to_u64()?),
+        });
+    }
+
+    if let Some(float) = Float::from_value(value) {
+        let non_finite_floats = context.options().non_finite_floats;
+
+        return Ok(match non_finite_floats.apply(ruby, float.to_f64())? {
+            Some(value) => Tree::Float(value),
+            None => Tree::Nil,
+        });
+    }
+
+    if let Some(string) = RString::from_value(value) {
+        return snapshot_string(ruby, context, string);
+    }
+
+    if let Some(symbol) = Symbol::from_value(value) {
+        context.check_symbol(ruby, symbol)?;
+
+        if let Some(symbol) = symbol.as_static() {
+            return Ok(Tree::Symbol(Cow::Borrowed(symbol.name()?)));
+        }
+
+        return Ok(Tree::Symbol(Cow::Owned(symbol.name()?.into_owned())));
+    }
+
+    if let Some(array) = RArray::from_value(value) {
+        let _nesting = context.enter(ruby, value)?;
+        context.check_array(ruby, array.len())?;
+        let mut elements = Vec::with_capacity(array.len());
+
+        for index in 0..array.len() {
+            context.check_interrupts(ruby)?;
+            elements.push(snapshot(ruby, context, array.entry(index as isize)?)?);
+        }
+
+        return Ok(Tree::Array(elements));
+    }
+
+    if let Some(hash) = RHash::from_value(value) {
+        let _nesting = context.enter(ruby, value)?;
+        context.check_hash(ruby, hash.len())?;
+        let flattened = entries(ruby, hash, context.options().duplicate_keys)?;
+        let mut entries = Vec::with_capacity(flattened.len() / 2);
+
+        for index in (0..flattened.len()).step_by(2) {
+            context.check_interrupts(ruby)?;
+            entries.push((
+                snapshot(ruby, context, flattened.entry(index as isize)?)?,
+                snapshot(ruby, context, flattened.entry(index as isize + 1)?)?,
+            ));
+        }
+
+        return Ok(Tree::Hash(entries));
+    }
+
+    Err(Error::new(
+        ruby.exception_type_error(),
+        format!(
+            "can't deserialize {}",
+            unsafe { value.classname() }.into_owned()
+        ),
+    )
+    .with_kind(ErrorKind::InvalidType))
+}
+
+fn snapshot_string(ruby: &Ruby, context: &Context<'_>, string: RString) -> Result<Tree, Error> {
+    context.check_string(ruby, string)?;
+
+    if let Some(borrowed) = unsafe { string.test_as_str() } {
+        return Ok(Tree::String(Cow::Owned(borrowed.to_owned())));
+    }
+
+    if string.enc_get() == ruby.ascii8bit_encindex() {
+        return Ok(Tree::Bytes(unsafe { string.as_slice() }.to_owned()));
+    }
+
+    Ok(Tree::String(Cow::Owned(string.to_string()?)))
+}
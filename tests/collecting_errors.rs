@@ -0,0 +1,135 @@
+use magnus::{eval, Error, RArray, RHash};
+use serde::Deserialize;
+use serde_magnus::{deserialize_collecting, DeserializeOptions, ErrorKind, PathSegment};
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct Order {
+    customer: String,
+    #[serde(default)]
+    note: String,
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct Item {
+    name: String,
+    quantity: u32,
+}
+
+#[test]
+fn test_collecting_errors() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+    let options = DeserializeOptions::new();
+
+    let input: RHash = eval!(
+        &ruby,
+        r#"
+        {
+          customer: "Alice",
+          note: 1,
+          items: [
+            { name: "Apple", quantity: 1 },
+            { name: "Pear", quantity: -1 },
+            { name: 2, quantity: "many" }
+          ]
+        }
+        "#
+    )?;
+    let errors = deserialize_collecting::<_, Order>(&ruby, input, &options).unwrap_err();
+    assert_eq!(4, errors.len());
+
+    let paths: Vec<_> = errors.iter().map(|error| error.path().to_vec()).collect();
+    assert_eq!(
+        vec![
+            vec![PathSegment::Key("note".into())],
+            vec![
+                PathSegment::Key("items".into()),
+                PathSegment::Index(1),
+                PathSegment::Key("quantity".into())
+            ],
+            vec![
+                PathSegment::Key("items".into()),
+                PathSegment::Index(2),
+                PathSegment::Key("name".into())
+            ],
+            vec![
+                PathSegment::Key("items".into()),
+                PathSegment::Index(2),
+                PathSegment::Key("quantity".into())
+            ],
+        ],
+        paths
+    );
+
+    let error = Error::from(errors);
+    assert!(error.is_kind_of(ruby.exception_runtime_error()));
+    assert!(error
+        .to_string()
+        .contains(r#"["items"][1]["quantity"]: RuntimeError: invalid value: integer `-1`"#));
+
+    let input: RHash = eval!(
+        &ruby,
+        r#"{ customer: "Bob", items: [{ name: "Plum", quantity: 3 }] }"#
+    )?;
+    let output: Order = deserialize_collecting(&ruby, input, &options)?;
+    assert_eq!(
+        Order {
+            customer: "Bob".into(),
+            note: "".into(),
+            items: vec![Item {
+                name: "Plum".into(),
+                quantity: 3
+            }]
+        },
+        output
+    );
+
+    let input: RHash = eval!(&ruby, r#"{ customer: 1, items: [] }"#)?;
+    let errors = deserialize_collecting::<_, Order>(&ruby, input, &options).unwrap_err();
+    assert_eq!(1, errors.len());
+    let error = Error::from(errors);
+    assert!(error.is_kind_of(ruby.exception_type_error()));
+
+    // Skipping an element of a tuple leaves it too short, which isn't reported again.
+    let input: RArray = eval!(&ruby, r#"[["a", 1], ["b", -1], ["c", 3]]"#)?;
+    let errors = deserialize_collecting::<_, Vec<(String, u32)>>(&ruby, input, &options)
+        .unwrap_err()
+        .into_vec();
+    assert_eq!(1, errors.len());
+    assert_eq!(
+        &[PathSegment::Index(1), PathSegment::Index(1)],
+        errors[0].path()
+    );
+
+    // Other objects are only enumerated once, however many attempts it takes.
+    let input: RHash = eval!(
+        &ruby,
+        r#"
+        $calls = 0
+
+        items = Class.new do
+          def each
+            $calls += 1
+            yield({ name: 2, quantity: 1 })
+            yield({ name: "Fig", quantity: "many" })
+          end
+        end.new
+
+        { customer: "Dan", items: items }
+        "#
+    )?;
+    let options = DeserializeOptions::new().enumerate(true);
+    let errors = deserialize_collecting::<_, Order>(&ruby, input, &options).unwrap_err();
+    assert_eq!(2, errors.len());
+    let calls: u64 = ruby.eval("$calls")?;
+    assert_eq!(1, calls);
+
+    let input: RHash = eval!(&ruby, r#"{ customer: "Carol", items: [[[1]]] }"#)?;
+    let options = DeserializeOptions::new().max_depth(2);
+    let errors = deserialize_collecting::<_, Order>(&ruby, input, &options).unwrap_err();
+    let errors = errors.into_vec();
+    assert_eq!(1, errors.len());
+    assert_eq!(ErrorKind::Custom, errors[0].kind());
+
+    Ok(())
+}
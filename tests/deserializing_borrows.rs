@@ -0,0 +1,62 @@
+use magnus::{eval, Error, RHash};
+use serde::Deserialize;
+use serde_bytes::Bytes;
+use serde_magnus::deserialize_borrowed;
+use std::borrow::Cow;
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct A<'a> {
+    message: &'a str,
+    #[serde(borrow)]
+    details: Cow<'a, str>,
+    #[serde(borrow)]
+    data: &'a Bytes,
+}
+
+#[test]
+fn test_deserializing_borrows() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+
+    let input = ruby.str_new("Hello, world!");
+    deserialize_borrowed(&ruby, input, |input| {
+        let output: &str = input.deserialize()?;
+        assert_eq!("Hello, world!", output);
+        Ok(())
+    })?;
+
+    // The borrow is unaffected by later changes to the original string.
+    deserialize_borrowed(&ruby, input, |borrowed| {
+        let output: &str = borrowed.deserialize()?;
+        input.cat(" Goodbye!");
+        assert_eq!("Hello, world!", output);
+        Ok(())
+    })?;
+    assert_eq!("Hello, world! Goodbye!", input.to_string()?);
+
+    let input: RHash = eval!(
+        &ruby,
+        "{ message: 'Hello, world!', details: 'Goodbye!', data: '☃' }"
+    )?;
+    deserialize_borrowed(&ruby, input, |input| {
+        let output: A = input.deserialize()?;
+        assert_eq!(
+            A {
+                message: "Hello, world!",
+                details: Cow::Borrowed("Goodbye!"),
+                data: Bytes::new(&[226, 152, 131]),
+            },
+            output
+        );
+        assert!(matches!(output.details, Cow::Borrowed(_)));
+        Ok(())
+    })?;
+
+    let input: RHash = eval!(&ruby, "{ message: 'Hello, world!'.encode('UTF-16LE') }")?;
+    let output: Result<(), Error> = deserialize_borrowed(&ruby, input, |input| {
+        input.deserialize::<A>()?;
+        Ok(())
+    });
+    assert!(output.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use magnus::{eval, Error, RHash, Value};
+use serde::Deserialize;
+use serde_magnus::{
+    deserialize, deserialize_detailed, deserialize_with, DeserializeOptions, ErrorKind,
+    NonFiniteFloats,
+};
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct Params {
+    id: u64,
+    quantity: i32,
+    price: f64,
+    active: bool,
+    note: Option<String>,
+}
+
+#[test]
+fn test_deserializing_coercions() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+    let options = DeserializeOptions::new().coerce(true);
+
+    let input: RHash = eval!(
+        &ruby,
+        "{ 'id' => '18446744073709551615', 'quantity' => ' -3 ', 'price' => '3.5', 'active' => 'on', 'note' => '' }"
+    )?;
+    assert!(deserialize::<_, Params>(&ruby, input).is_err());
+
+    let output: Params = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(
+        Params {
+            id: u64::MAX,
+            quantity: -3,
+            price: 3.5,
+            active: true,
+            note: Some("".into()),
+        },
+        output
+    );
+
+    let output: Params =
+        deserialize_with(&ruby, input, &options.clone().empty_strings_as_none(true))?;
+    assert_eq!(None, output.note);
+
+    for (input, expected) in [
+        ("'1'", true),
+        ("'t'", true),
+        ("'TRUE'", true),
+        ("'0'", false),
+        ("'f'", false),
+        ("'off'", false),
+    ] {
+        let input: Value = ruby.eval(input)?;
+        let output: bool = deserialize_with(&ruby, input, &options)?;
+        assert_eq!(expected, output);
+    }
+
+    let input: Value = eval!(&ruby, "'yes please'")?;
+    let error = deserialize_detailed::<_, bool>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::InvalidValue, error.kind());
+    assert_eq!(Some("string \"yes please\""), error.unexpected());
+
+    let input: Value = eval!(&ruby, "'42abc'")?;
+    let error = deserialize_detailed::<_, u64>(&ruby, input, &options).unwrap_err();
+    assert_eq!(Some("string \"42abc\""), error.unexpected());
+
+    let input: Value = eval!(&ruby, "''")?;
+    assert!(deserialize_with::<_, f64>(&ruby, input, &options).is_err());
+
+    let nil_options = options.clone().non_finite_floats(NonFiniteFloats::Nil);
+    for input in ["'NaN'", "'inf'", "'-Infinity'", "'1e400'"] {
+        let input: Value = ruby.eval(input)?;
+        let error = deserialize_detailed::<_, f64>(&ruby, input, &options).unwrap_err();
+        assert_eq!(ErrorKind::InvalidValue, error.kind());
+        assert!(deserialize_with::<_, Option<f64>>(&ruby, input, &nil_options).is_err());
+    }
+
+    let input: Value = eval!(&ruby, "42.0")?;
+    let output: u8 = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(42, output);
+
+    let input: Value = eval!(&ruby, "42.5")?;
+    assert!(deserialize_with::<_, u8>(&ruby, input, &options).is_err());
+
+    let input: Value = eval!(&ruby, "42.0")?;
+    let strict = options.clone().strict_numbers(true);
+    assert!(deserialize_with::<_, u8>(&ruby, input, &strict).is_err());
+
+    let input: Value = eval!(&ruby, "'300'")?;
+    let error = deserialize_detailed::<_, u8>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::InvalidValue, error.kind());
+
+    let input: Value = eval!(&ruby, "'170141183460469231731687303715884105727'")?;
+    let output: i128 = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(i128::MAX, output);
+
+    let input: Value = eval!(&ruby, "'-170141183460469231731687303715884105728'")?;
+    let output: i128 = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(i128::MIN, output);
+
+    let input: Value = eval!(&ruby, "'340282366920938463463374607431768211455'")?;
+    let output: u128 = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(u128::MAX, output);
+
+    let input: Value = eval!(&ruby, "'340282366920938463463374607431768211456'")?;
+    let error = deserialize_detailed::<_, u128>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::InvalidValue, error.kind());
+
+    let input: Value = eval!(&ruby, "'170141183460469231731687303715884105727'")?;
+    let error = deserialize_detailed::<_, u64>(&ruby, input, &options).unwrap_err();
+    assert_eq!(
+        Some("string \"170141183460469231731687303715884105727\""),
+        error.unexpected()
+    );
+
+    let input: Value = eval!(&ruby, "'42'")?;
+    let output: String = deserialize_with(&ruby, input, &options)?;
+    assert_eq!("42", output);
+
+    Ok(())
+}
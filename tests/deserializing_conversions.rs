@@ -0,0 +1,111 @@
+use magnus::{eval, Error, Value};
+use serde::{de::IgnoredAny, Deserialize};
+use serde_magnus::{deserialize, deserialize_with, DeserializeOptions};
+use std::collections::HashMap;
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct Settings {
+    theme: String,
+    size: u64,
+}
+
+#[test]
+fn test_deserializing_conversions() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+
+    let input: Value = eval!(
+        &ruby,
+        r#"
+        Class.new do
+          def to_hash
+            { theme: "dark", size: 12 }
+          end
+        end.new
+        "#
+    )?;
+
+    let output: Settings = deserialize(&ruby, input)?;
+    assert_eq!(
+        Settings {
+            theme: "dark".into(),
+            size: 12
+        },
+        output
+    );
+
+    let output: HashMap<String, IgnoredAny> = deserialize(&ruby, input)?;
+    assert_eq!(2, output.len());
+
+    let input: Value = eval!(
+        &ruby,
+        r#"
+        Class.new do
+          def to_ary
+            [1, 2, 3]
+          end
+        end.new
+        "#
+    )?;
+
+    let output: Vec<u64> = deserialize(&ruby, input)?;
+    assert_eq!(vec![1, 2, 3], output);
+
+    let output: (u64, u64, u64) = deserialize(&ruby, input)?;
+    assert_eq!((1, 2, 3), output);
+
+    let input: Value = eval!(
+        &ruby,
+        r#"
+        Class.new do
+          def to_a
+            [1, 2, 3]
+          end
+        end.new
+        "#
+    )?;
+    assert!(deserialize::<_, Vec<u64>>(&ruby, input).is_err());
+
+    let options = DeserializeOptions::new().explicit_conversions(true);
+    let output: Vec<u64> = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(vec![1, 2, 3], output);
+
+    let input: Value = eval!(
+        &ruby,
+        r#"
+        Class.new do
+          def to_h
+            { theme: "light", size: 10 }
+          end
+        end.new
+        "#
+    )?;
+    assert!(deserialize::<_, Settings>(&ruby, input).is_err());
+
+    let output: Settings = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(
+        Settings {
+            theme: "light".into(),
+            size: 10
+        },
+        output
+    );
+
+    // Arrays of pairs aren't converted to hashes.
+    let input: Value = eval!(&ruby, r#"[["theme", "dark"], ["size", 12]]"#)?;
+    assert!(deserialize_with::<_, HashMap<String, IgnoredAny>>(&ruby, input, &options).is_err());
+
+    let input: Value = eval!(
+        &ruby,
+        r#"
+        Class.new do
+          def to_hash
+            raise ArgumentError, "unpermitted parameters"
+          end
+        end.new
+        "#
+    )?;
+    let error = deserialize::<_, Settings>(&ruby, input).unwrap_err();
+    assert!(error.is_kind_of(ruby.exception_arg_error()));
+
+    Ok(())
+}
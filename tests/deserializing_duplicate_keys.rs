@@ -0,0 +1,110 @@
+use magnus::{eval, Error, RHash};
+use serde::{de::IgnoredAny, Deserialize};
+use serde_magnus::{
+    deserialize, deserialize_detailed, deserialize_with, DeserializeOptions, DuplicateKeys,
+    ErrorKind,
+};
+use std::collections::HashMap;
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct User {
+    name: String,
+    email: Option<String>,
+}
+
+#[test]
+fn test_deserializing_duplicate_keys() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+
+    let input: RHash = eval!(
+        &ruby,
+        "{ 'name' => 'a', email: 'a@example.com', name: 'b' }"
+    )?;
+
+    assert!(deserialize::<_, User>(&ruby, input).is_err());
+
+    let options = DeserializeOptions::new().duplicate_keys(DuplicateKeys::Error);
+    let error = deserialize_detailed::<_, User>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::DuplicateKey, error.kind());
+    assert_eq!(Some("name"), error.unexpected());
+    assert_eq!(
+        r#"RuntimeError: duplicate key `name`, given as both :name and "name""#,
+        error.to_string()
+    );
+
+    let error =
+        deserialize_detailed::<_, HashMap<String, String>>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::DuplicateKey, error.kind());
+
+    for (duplicate_keys, expected) in [
+        (DuplicateKeys::PreferSymbol, "b"),
+        (DuplicateKeys::PreferString, "a"),
+        (DuplicateKeys::LastWins, "b"),
+    ] {
+        let options = DeserializeOptions::new().duplicate_keys(duplicate_keys);
+
+        let output: User = deserialize_with(&ruby, input, &options)?;
+        assert_eq!(
+            User {
+                name: expected.into(),
+                email: Some("a@example.com".into())
+            },
+            output
+        );
+
+        let output: HashMap<String, String> = deserialize_with(&ruby, input, &options)?;
+        assert_eq!(2, output.len());
+        assert_eq!(Some(&expected.into()), output.get("name"));
+    }
+
+    let input: RHash = eval!(&ruby, "{ name: 'b', 'name' => 'a' }")?;
+    let options = DeserializeOptions::new().duplicate_keys(DuplicateKeys::LastWins);
+
+    let output: User = deserialize_with(&ruby, input, &options)?;
+    assert_eq!("a", output.name);
+
+    let output: HashMap<String, String> = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(Some(&"a".into()), output.get("name"));
+
+    let input: RHash = eval!(
+        &ruby,
+        "{ name: 'a', 'email' => 'a@example.com', 'name' => 'b', email: 'b@example.com' }"
+    )?;
+    let output: User = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(
+        User {
+            name: "b".into(),
+            email: Some("b@example.com".into())
+        },
+        output
+    );
+
+    let input: RHash = eval!(&ruby, "{ name: 'b', 'email' => 'b@example.com' }")?;
+    let options = DeserializeOptions::new().duplicate_keys(DuplicateKeys::Error);
+    let output: User = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(
+        User {
+            name: "b".into(),
+            email: Some("b@example.com".into())
+        },
+        output
+    );
+
+    // Symbols whose names aren't valid UTF-8 are left alone, rather than failing.
+    let input: RHash = eval!(
+        &ruby,
+        r#"{ "\xff".b.to_sym => 1, "\xff".b => 2, name: 'a' }"#
+    )?;
+    let output: IgnoredAny = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(IgnoredAny, output);
+
+    let input: RHash = eval!(
+        &ruby,
+        "(1..1000).to_h { |i| [\"key#{i}\", i] }.merge(key500: 0, other: 1)"
+    )?;
+    let error =
+        deserialize_detailed::<_, HashMap<String, u64>>(&ruby, input, &options).unwrap_err();
+    assert_eq!(Some("key500"), error.unexpected());
+
+    Ok(())
+}
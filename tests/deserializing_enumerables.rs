@@ -0,0 +1,111 @@
+use magnus::{eval, Error, Value};
+use serde_magnus::{deserialize, deserialize_with, DeserializeOptions, ErrorKind};
+use std::collections::BTreeSet;
+
+#[test]
+fn test_deserializing_enumerables() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+
+    // Only enumerated when enabled, since `each` may have side effects.
+    let input: Value = eval!(
+        &ruby,
+        r#"
+        $calls = 0
+
+        Class.new do
+          def each
+            $calls += 1
+            yield 1
+          end
+        end.new
+        "#
+    )?;
+    let error = deserialize::<_, Vec<u64>>(&ruby, input).unwrap_err();
+    assert_eq!(ErrorKind::InvalidType, error.kind());
+    let calls: u64 = ruby.eval("$calls")?;
+    assert_eq!(0, calls);
+
+    let options = DeserializeOptions::new().enumerate(true);
+
+    let input: Value = eval!(&ruby, "require 'set'; Set[3, 1, 2]")?;
+    let output: BTreeSet<u64> = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(BTreeSet::from([1, 2, 3]), output);
+
+    let input: Value = eval!(&ruby, "1..3")?;
+    let output: Vec<u64> = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(vec![1, 2, 3], output);
+
+    let input: Value = eval!(&ruby, "%w[a b].each_with_index")?;
+    let output: Vec<(String, u64)> = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(vec![("a".into(), 0), ("b".into(), 1)], output);
+
+    let input: Value = eval!(&ruby, "(1..).lazy.map { |n| n * 2 }.take(3)")?;
+    let output: Vec<u64> = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(vec![2, 4, 6], output);
+
+    let input: Value = eval!(
+        &ruby,
+        r#"
+        Class.new do
+          def each
+            yield
+            yield "one", 1
+            yield "two", 2
+          end
+        end.new
+        "#
+    )?;
+    let output: Vec<Option<(String, u64)>> = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(
+        vec![None, Some(("one".into(), 1)), Some(("two".into(), 2))],
+        output
+    );
+
+    let input: Value = eval!(&ruby, "(1..).each")?;
+    let error = deserialize_with::<_, Vec<u64>>(&ruby, input, &options).unwrap_err();
+    assert_eq!(
+        "ArgumentError: enumeration yielded more than 1000000 values",
+        error.to_string()
+    );
+
+    let input: Value = eval!(&ruby, "(1..).lazy.map { |n| n * 2 }")?;
+    let options = DeserializeOptions::new().enumerate(true).max_enumerated(2);
+    let error = deserialize_with::<_, Vec<u64>>(&ruby, input, &options).unwrap_err();
+    assert_eq!(
+        "ArgumentError: enumeration yielded more than 2 values",
+        error.to_string()
+    );
+
+    let options = DeserializeOptions::new()
+        .enumerate(true)
+        .max_array_length(3);
+    let error = deserialize_with::<_, Vec<u64>>(&ruby, input, &options).unwrap_err();
+    assert_eq!(
+        "ArgumentError: array length 4 exceeds the limit of 3",
+        error.to_string()
+    );
+
+    let options = DeserializeOptions::new().enumerate(true).max_elements(5);
+    let error = deserialize_with::<_, Vec<u64>>(&ruby, input, &options).unwrap_err();
+    assert_eq!(
+        "ArgumentError: input has more than 5 elements",
+        error.to_string()
+    );
+
+    let input: Value = eval!(
+        &ruby,
+        r#"
+        Class.new do
+          def each
+            yield 1
+            raise IOError, "closed stream"
+          end
+        end.new
+        "#
+    )?;
+    let options = DeserializeOptions::new().enumerate(true);
+    let error = deserialize_with::<_, Vec<u64>>(&ruby, input, &options).unwrap_err();
+    assert!(error.is_kind_of(ruby.exception_io_error()));
+
+    Ok(())
+}
@@ -1,5 +1,8 @@
-use magnus::Error;
-use serde_magnus::deserialize;
+use magnus::{eval, Error, Value};
+use serde_magnus::{
+    deserialize, deserialize_detailed, deserialize_with, DeserializeOptions, ErrorKind,
+    NonFiniteFloats,
+};
 
 #[test]
 fn test_deserializing_floats() -> Result<(), Error> {
@@ -8,5 +11,33 @@ fn test_deserializing_floats() -> Result<(), Error> {
     let output: f64 = deserialize(&ruby, ruby.float_from_f64(1.23))?;
     assert_eq!(1.23, output);
 
+    let input: Value = eval!(&ruby, "[Float::NAN, Float::INFINITY, -Float::INFINITY]")?;
+    let output: Vec<f64> = deserialize(&ruby, input)?;
+    assert!(output[0].is_nan());
+    assert_eq!(vec![f64::INFINITY, f64::NEG_INFINITY], output[1..]);
+
+    let options = DeserializeOptions::new().non_finite_floats(NonFiniteFloats::Nil);
+    let output: Vec<Option<f32>> = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(vec![None, None, None], output);
+
+    let input: Value = eval!(&ruby, "Float::INFINITY")?;
+    assert!(deserialize_with::<_, f64>(&ruby, input, &options).is_err());
+
+    let input: Value = eval!(&ruby, "1.5")?;
+    let output: Option<f64> = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(Some(1.5), output);
+
+    let options = DeserializeOptions::new().non_finite_floats(NonFiniteFloats::Error);
+    let input: Value = eval!(&ruby, "{ 'a' => 1.5, 'b' => -Float::INFINITY }")?;
+    let error =
+        deserialize_detailed::<_, std::collections::HashMap<String, f64>>(&ruby, input, &options)
+            .unwrap_err();
+    assert_eq!(ErrorKind::InvalidValue, error.kind());
+    assert_eq!("FloatDomainError: -Infinity", error.to_string());
+
+    let input: Value = eval!(&ruby, "'NaN'")?;
+    let options = options.coerce(true);
+    assert!(deserialize_with::<_, f64>(&ruby, input, &options).is_err());
+
     Ok(())
 }
@@ -0,0 +1,54 @@
+use magnus::{eval, Error, RArray, RHash};
+use serde::Deserialize;
+use serde_magnus::deserialize_into;
+
+// Derived implementations only deserialize in place with serde_derive's `deserialize_in_place`
+// feature, which the dev-dependencies enable.
+#[derive(Deserialize, PartialEq, Debug)]
+struct Post {
+    title: String,
+    tags: Vec<String>,
+}
+
+#[test]
+fn test_deserializing_in_place() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+
+    let mut output = String::with_capacity(100);
+    let buffer = output.as_ptr();
+    deserialize_into(&ruby, ruby.str_new("Hello, world!"), &mut output)?;
+    assert_eq!("Hello, world!", output);
+    assert_eq!(buffer, output.as_ptr());
+
+    let mut output: Vec<String> = vec!["Goodbye!".into(), "Goodbye!".into()];
+    let buffers: Vec<*const u8> = output.iter().map(|string| string.as_ptr()).collect();
+    let input: RArray = eval!(&ruby, r#"["Hello,", "world!", "Again!"]"#)?;
+    deserialize_into(&ruby, input, &mut output)?;
+    assert_eq!(vec!["Hello,", "world!", "Again!"], output);
+    assert_eq!(buffers[0], output[0].as_ptr());
+    assert_eq!(buffers[1], output[1].as_ptr());
+
+    let mut output = Post {
+        title: String::with_capacity(100),
+        tags: vec!["Goodbye!".into()],
+    };
+    let title = output.title.as_ptr();
+    let tag = output.tags[0].as_ptr();
+    let input: RHash = eval!(&ruby, r#"{ title: "Hello, world!", tags: ["Hello!"] }"#)?;
+    deserialize_into(&ruby, input, &mut output)?;
+    assert_eq!(
+        Post {
+            title: "Hello, world!".into(),
+            tags: vec!["Hello!".into()]
+        },
+        output
+    );
+    assert_eq!(title, output.title.as_ptr());
+    assert_eq!(tag, output.tags[0].as_ptr());
+
+    let mut output: Vec<i64> = vec![0; 3];
+    let input: RArray = eval!(&ruby, "[1, :two]")?;
+    assert!(deserialize_into(&ruby, input, &mut output).is_err());
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use magnus::{eval, Error, RArray, RHash};
+use serde::Deserialize;
+use serde_magnus::{deserialize_with, DeserializeOptions};
+use std::collections::HashMap;
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct A {
+    message: String,
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+enum B {
+    A(u64),
+}
+
+#[test]
+fn test_deserializing_limits() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+
+    let input: RHash = eval!(
+        &ruby,
+        "{ message: 'Hello, world!', tags: ['greeting', 'example'] }"
+    )?;
+
+    let options = DeserializeOptions::new()
+        .max_elements(4)
+        .max_array_length(2)
+        .max_hash_size(2)
+        .max_string_bytes(13);
+    let output: A = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(
+        A {
+            message: "Hello, world!".into(),
+            tags: vec!["greeting".into(), "example".into()]
+        },
+        output
+    );
+
+    let options = DeserializeOptions::new().max_elements(3);
+    let output: Result<A, Error> = deserialize_with(&ruby, input, &options);
+    assert_eq!(
+        "ArgumentError: input has more than 3 elements",
+        output.unwrap_err().to_string()
+    );
+
+    let options = DeserializeOptions::new().max_array_length(1);
+    let output: Result<A, Error> = deserialize_with(&ruby, input, &options);
+    assert_eq!(
+        "ArgumentError: array length 2 exceeds the limit of 1",
+        output.unwrap_err().to_string()
+    );
+
+    let options = DeserializeOptions::new().max_hash_size(1);
+    let output: Result<A, Error> = deserialize_with(&ruby, input, &options);
+    assert_eq!(
+        "ArgumentError: hash size 2 exceeds the limit of 1",
+        output.unwrap_err().to_string()
+    );
+
+    let input: RHash = eval!(&ruby, "{ 'a' => 1, 'b' => 2, 'c' => 3 }")?;
+    let output: Result<HashMap<String, u64>, Error> = deserialize_with(&ruby, input, &options);
+    assert_eq!(
+        "ArgumentError: hash size 3 exceeds the limit of 1",
+        output.unwrap_err().to_string()
+    );
+
+    let options = DeserializeOptions::new().max_string_bytes(12);
+    let input: RArray = eval!(&ruby, "['Hello!', 'Hello, world!']")?;
+    let output: Result<Vec<String>, Error> = deserialize_with(&ruby, input, &options);
+    assert_eq!(
+        "ArgumentError: string of 13 bytes exceeds the limit of 12",
+        output.unwrap_err().to_string()
+    );
+
+    // Symbols and hash keys count as strings, too.
+    let input: RArray = eval!(&ruby, "[:'Hello!', :'Hello, world!']")?;
+    let output: Result<Vec<String>, Error> = deserialize_with(&ruby, input, &options);
+    assert_eq!(
+        "ArgumentError: string of 13 bytes exceeds the limit of 12",
+        output.unwrap_err().to_string()
+    );
+
+    let input: RHash = eval!(&ruby, "{ :'Hello, world!' => 1 }")?;
+    let output: Result<HashMap<String, u64>, Error> = deserialize_with(&ruby, input, &options);
+    assert_eq!(
+        "ArgumentError: string of 13 bytes exceeds the limit of 12",
+        output.unwrap_err().to_string()
+    );
+
+    let input: RHash = eval!(
+        &ruby,
+        "{ message: 'Hello!', tags: [], 'Hello, world!' => 1 }"
+    )?;
+    let output: Result<A, Error> = deserialize_with(&ruby, input, &options);
+    assert_eq!(
+        "ArgumentError: string of 13 bytes exceeds the limit of 12",
+        output.unwrap_err().to_string()
+    );
+
+    let input: RHash = eval!(&ruby, "{ 'Hello, world!' => 1 }")?;
+    let output: Result<B, Error> = deserialize_with(&ruby, input, &options);
+    assert_eq!(
+        "ArgumentError: string of 13 bytes exceeds the limit of 12",
+        output.unwrap_err().to_string()
+    );
+
+    Ok(())
+}
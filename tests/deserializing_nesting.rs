@@ -0,0 +1,82 @@
+use magnus::{eval, Error, RArray, RHash};
+use serde::Deserialize;
+use serde_magnus::{deserialize, deserialize_with, snapshot, DeserializeOptions};
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug)]
+struct Nested(#[allow(dead_code)] Vec<Nested>);
+
+#[derive(Deserialize, Debug)]
+struct Keyed(#[allow(dead_code)] HashMap<String, Keyed>);
+
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+enum A {
+    A(Vec<A>),
+    B { a: Box<A> },
+}
+
+#[test]
+fn test_deserializing_nesting() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+
+    // Without a limit, nesting is only bounded by the stack.
+    let input: RArray = eval!(&ruby, "200.times.reduce([]) { |a, _| [a] }")?;
+    let _: Nested = deserialize(&ruby, input)?;
+
+    let options = DeserializeOptions::new().max_depth(128);
+
+    let input: RArray = eval!(&ruby, "a = []; a << a")?;
+    let output: Result<Nested, Error> = deserialize_with(&ruby, input, &options);
+    assert_eq!(
+        "ArgumentError: nesting of 129 is too deep",
+        output.unwrap_err().to_string()
+    );
+
+    let input: RArray = eval!(&ruby, "128.times.reduce([]) { |a, _| [a] }")?;
+    let output: Result<Nested, Error> = deserialize_with(&ruby, input, &options);
+    assert_eq!(
+        "ArgumentError: nesting of 129 is too deep",
+        output.unwrap_err().to_string()
+    );
+
+    let input: RArray = eval!(&ruby, "127.times.reduce([]) { |a, _| [a] }")?;
+    let _: Nested = deserialize_with(&ruby, input, &options)?;
+
+    let options = DeserializeOptions::new().detect_cycles(true);
+
+    let input: RHash = eval!(&ruby, "h = {}; h[:h] = h; h")?;
+    let output: Result<Keyed, Error> = deserialize_with(&ruby, input, &options);
+    assert_eq!(
+        "ArgumentError: can't deserialize recursive Hash",
+        output.unwrap_err().to_string()
+    );
+
+    let input: RHash = eval!(&ruby, "h = { 'B' => { a: nil } }; h['B'][:a] = h; h")?;
+    let output: Result<A, Error> = deserialize_with(&ruby, input, &options);
+    assert_eq!(
+        "ArgumentError: can't deserialize recursive Hash",
+        output.unwrap_err().to_string()
+    );
+
+    // The same array may appear more than once, as long as it doesn't contain itself.
+    let input: RArray = eval!(&ruby, "a = []; [a, a, [a]]")?;
+    let output: Vec<Nested> = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(3, output.len());
+
+    let input: RArray = eval!(&ruby, "a = []; a << a")?;
+    assert_eq!(
+        "ArgumentError: nesting of 129 is too deep",
+        snapshot(&ruby, input, &DeserializeOptions::new().max_depth(128))
+            .unwrap_err()
+            .to_string()
+    );
+
+    let input: RArray = eval!(&ruby, "a = []; a << a")?;
+    assert_eq!(
+        "ArgumentError: can't deserialize recursive Array",
+        snapshot(&ruby, input, &options).unwrap_err().to_string()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use magnus::{eval, Error, Value};
+use serde_magnus::{
+    deserialize, deserialize_detailed, deserialize_with, DeserializeOptions, ErrorKind,
+};
+
+#[test]
+fn test_deserializing_strict_numbers() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+    let options = DeserializeOptions::new().strict_numbers(true);
+
+    let input: Value = eval!(&ruby, "0.1")?;
+    let output: f32 = deserialize(&ruby, input)?;
+    assert_eq!(0.1, output);
+
+    let error = deserialize_detailed::<_, f32>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::InvalidValue, error.kind());
+    assert_eq!(Some("floating point `0.1`"), error.unexpected());
+
+    let input: Value = eval!(&ruby, "-2.5")?;
+    let output: f32 = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(-2.5, output);
+
+    let input: Value = eval!(&ruby, "Float::NAN")?;
+    let output: f32 = deserialize_with(&ruby, input, &options)?;
+    assert!(output.is_nan());
+
+    let input: Value = eval!(&ruby, "0.1")?;
+    let output: f64 = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(0.1, output);
+
+    let input: Value = eval!(&ruby, "3")?;
+    let output: f64 = deserialize(&ruby, input)?;
+    assert_eq!(3.0, output);
+
+    let error = deserialize_detailed::<_, f64>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::InvalidType, error.kind());
+    assert_eq!(Some("integer `3`"), error.unexpected());
+
+    let error = deserialize_detailed::<_, f32>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::InvalidType, error.kind());
+
+    let input: Value = eval!(&ruby, "2 ** 64")?;
+    let error = deserialize_detailed::<_, f64>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::InvalidType, error.kind());
+
+    let input: Value = eval!(&ruby, "3")?;
+    let output: u64 = deserialize_with(&ruby, input, &options)?;
+    assert_eq!(3, output);
+
+    let input: Value = eval!(&ruby, "3.0")?;
+    let error = deserialize_detailed::<_, u64>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::InvalidType, error.kind());
+    assert_eq!(Some("floating point `3.0`"), error.unexpected());
+
+    let input: Value = eval!(&ruby, "[1.5, 2]")?;
+    let error = deserialize_detailed::<_, Vec<f64>>(&ruby, input, &options).unwrap_err();
+    assert_eq!(Some("integer `2`"), error.unexpected());
+
+    Ok(())
+}
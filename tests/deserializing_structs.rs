@@ -1,6 +1,7 @@
 use magnus::{eval, Error, IntoValue, RArray, RHash};
 use serde::Deserialize;
 use serde_magnus::deserialize;
+use std::collections::HashMap;
 
 #[derive(Deserialize, PartialEq, Debug)]
 struct A;
@@ -16,6 +17,110 @@ struct D {
     message: String,
 }
 
+#[derive(Deserialize, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+struct E {
+    message: String,
+    #[serde(default)]
+    count: u64,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct F {
+    #[serde(flatten)]
+    inner: D,
+    count: u64,
+}
+
+#[derive(PartialEq, Debug)]
+struct G {
+    message: String,
+    extra: Vec<(String, u64)>,
+}
+
+impl<'de> Deserialize<'de> for G {
+    fn deserialize<D>(deserializer: D) -> Result<G, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = G;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("struct G")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<G, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let mut message = None;
+                let mut extra = Vec::new();
+
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == "message" {
+                        message = Some(map.next_value()?);
+                    } else {
+                        extra.push((key, map.next_value()?));
+                    }
+                }
+
+                Ok(G {
+                    message: message.ok_or_else(|| serde::de::Error::missing_field("message"))?,
+                    extra,
+                })
+            }
+        }
+
+        deserializer.deserialize_struct("G", &["message"], Visitor)
+    }
+}
+
+/// Collects the keys of unknown fields, deserializing their values as maps of ignored values.
+#[derive(PartialEq, Debug)]
+struct H {
+    extra: Vec<String>,
+}
+
+impl<'de> Deserialize<'de> for H {
+    fn deserialize<D>(deserializer: D) -> Result<H, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = H;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("struct H")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<H, A::Error>
+            where
+                A: serde::de::MapAccess<'de>,
+            {
+                let mut extra = Vec::new();
+
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == "message" {
+                        map.next_value::<serde::de::IgnoredAny>()?;
+                    } else {
+                        map.next_value::<HashMap<String, serde::de::IgnoredAny>>()?;
+                        extra.push(key);
+                    }
+                }
+
+                Ok(H { extra })
+            }
+        }
+
+        deserializer.deserialize_struct("H", &["message"], Visitor)
+    }
+}
+
 #[test]
 fn test_deserializing_structs() -> Result<(), Error> {
     let ruby = unsafe { magnus::embed::init() };
@@ -39,5 +144,106 @@ fn test_deserializing_structs() -> Result<(), Error> {
         output
     );
 
+    let input: RHash = eval!(&ruby, "{ 'message' => 'Hello, world!' }")?;
+    let output: D = deserialize(&ruby, input)?;
+    assert_eq!(
+        D {
+            message: "Hello, world!".into()
+        },
+        output
+    );
+
+    let input: RHash = eval!(
+        &ruby,
+        "(1..200).to_h { |i| [:\"key#{i}\", i] }.merge(message: 'Hello, world!')"
+    )?;
+    let output: D = deserialize(&ruby, input)?;
+    assert_eq!(
+        D {
+            message: "Hello, world!".into()
+        },
+        output
+    );
+
+    let input: RHash = eval!(
+        &ruby,
+        "{ message: 'Hello, world!', 'message' => 'Goodbye!' }"
+    )?;
+    let output: Result<D, Error> = deserialize(&ruby, input);
+    assert_eq!(
+        "RuntimeError: duplicate field `message`",
+        output.unwrap_err().to_string()
+    );
+
+    let input: RHash = eval!(&ruby, "{ message: 'Hello, world!' }")?;
+    let output: E = deserialize(&ruby, input)?;
+    assert_eq!(
+        E {
+            message: "Hello, world!".into(),
+            count: 0
+        },
+        output
+    );
+
+    let input: RHash = eval!(&ruby, "{ message: 'Hello, world!', cuont: 1 }")?;
+    let output: Result<E, Error> = deserialize(&ruby, input);
+    assert_eq!(
+        "RuntimeError: unknown field `cuont`, expected `message` or `count`",
+        output.unwrap_err().to_string()
+    );
+
+    let input: RHash = eval!(
+        &ruby,
+        "{ message: 'Hello, world!', 'co'.+('unt').to_sym => 1 }"
+    )?;
+    let output: F = deserialize(&ruby, input)?;
+    assert_eq!(
+        F {
+            inner: D {
+                message: "Hello, world!".into()
+            },
+            count: 1
+        },
+        output
+    );
+
+    let input: RHash = eval!(
+        &ruby,
+        "{ first: 1, message: 'Hello, world!', 'second' => 2, third: 3 }"
+    )?;
+    let output: G = deserialize(&ruby, input)?;
+    assert_eq!(
+        G {
+            message: "Hello, world!".into(),
+            extra: vec![
+                ("first".into(), 1),
+                ("second".into(), 2),
+                ("third".into(), 3)
+            ]
+        },
+        output
+    );
+
+    // Keys that aren't valid UTF-8 don't name fields, but don't fail the struct either.
+    let input: RHash = eval!(
+        &ruby,
+        r#"{ "\xff".b.to_sym => 1, "\xfe".b => 2, message: 'Hello, world!' }"#
+    )?;
+    let output: D = deserialize(&ruby, input)?;
+    assert_eq!(
+        D {
+            message: "Hello, world!".into()
+        },
+        output
+    );
+
+    // Values ignored within an unknown key's value don't count as ignoring the value itself.
+    let input: RHash = eval!(
+        &ruby,
+        "{ first: { ignored: nil }, message: 'Hello, world!', second: { ignored: nil } }"
+    )?;
+    let output: H = deserialize(&ruby, input)?;
+    assert_eq!(vec![String::from("first"), "second".into()], output.extra);
+
     Ok(())
 }
@@ -0,0 +1,132 @@
+use magnus::{eval, Error, RArray, RHash, Value};
+use serde::Deserialize;
+use serde_magnus::{
+    deserialize_without_gvl, snapshot, DeserializeOptions, DuplicateKeys, NonFiniteFloats, Tree,
+};
+use std::collections::HashMap;
+
+#[derive(Deserialize, PartialEq, Debug)]
+enum A {
+    A,
+    B(u64),
+    C(u64, bool, String),
+    D { message: String },
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct B {
+    message: String,
+    #[serde(with = "serde_bytes")]
+    data: Vec<u8>,
+    count: Option<u64>,
+}
+
+#[test]
+fn test_deserializing_trees() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+    let options = DeserializeOptions::default();
+
+    let input: Value = eval!(&ruby, "[nil, true, 2 ** 64 - 1, 1.5, 'a', :b, \"\\xff\".b]")?;
+    assert_eq!(
+        Tree::Array(vec![
+            Tree::Nil,
+            Tree::Bool(true),
+            Tree::UnsignedInteger(u64::MAX),
+            Tree::Float(1.5),
+            Tree::String("a".into()),
+            Tree::Symbol("b".into()),
+            Tree::Bytes(vec![0xff]),
+        ]),
+        snapshot(&ruby, input, &options)?
+    );
+
+    let input: RArray = eval!(
+        &ruby,
+        r#"
+        [
+          "A",
+          { "B" => 123 },
+          { "C" => [ 123, true, "Hello, world!" ] },
+          { "D" => { message: "Hello, world!" } }
+        ]
+        "#
+    )?;
+    let tree = snapshot(&ruby, input, &options)?;
+    let output = std::thread::spawn(move || Vec::<A>::deserialize(tree).unwrap())
+        .join()
+        .unwrap();
+    assert_eq!(
+        vec![
+            A::A,
+            A::B(123),
+            A::C(123, true, "Hello, world!".into()),
+            A::D {
+                message: "Hello, world!".into()
+            }
+        ],
+        output
+    );
+
+    let input: RHash = eval!(
+        &ruby,
+        r#"{ message: "Hello, world!", data: "\xff".b, count: nil }"#
+    )?;
+    let output: B = deserialize_without_gvl(&ruby, input, &options)?;
+    assert_eq!(
+        B {
+            message: "Hello, world!".into(),
+            data: vec![0xff],
+            count: None,
+        },
+        output
+    );
+
+    let input: RHash = eval!(&ruby, r#"{ "Stop" => "Go", "Yes" => "No" }"#)?;
+    let output: HashMap<String, String> = deserialize_without_gvl(&ruby, input, &options)?;
+    assert_eq!(Some("Go"), output.get("Stop").map(String::as_str));
+    assert_eq!(Some("No"), output.get("Yes").map(String::as_str));
+
+    let input: Value = eval!(&ruby, "Object.new")?;
+    assert!(snapshot(&ruby, input, &options).is_err());
+
+    // The options' limits and duplicate key policy are applied while copying.
+    let input: RArray = eval!(&ruby, "[1, 2, 3]")?;
+    let options = DeserializeOptions::new().max_array_length(2);
+    assert_eq!(
+        "ArgumentError: array length 3 exceeds the limit of 2",
+        snapshot(&ruby, input, &options).unwrap_err().to_string()
+    );
+
+    let input: RHash = eval!(&ruby, r#"{ message: "Hello", "message" => "Goodbye" }"#)?;
+    let options = DeserializeOptions::new().duplicate_keys(DuplicateKeys::PreferString);
+    assert_eq!(
+        Tree::Hash(vec![(
+            Tree::String("message".into()),
+            Tree::String("Goodbye".into())
+        )]),
+        snapshot(&ruby, input, &options)?
+    );
+
+    let input: RArray = eval!(&ruby, "[1.5, Float::NAN]")?;
+    let output: Vec<f64> = deserialize_without_gvl(&ruby, input, &DeserializeOptions::new())?;
+    assert_eq!(1.5, output[0]);
+    assert!(output[1].is_nan());
+
+    let options = DeserializeOptions::new().non_finite_floats(NonFiniteFloats::Nil);
+    assert_eq!(
+        Tree::Array(vec![Tree::Float(1.5), Tree::Nil]),
+        snapshot(&ruby, input, &options)?
+    );
+    let output: Vec<Option<f64>> = deserialize_without_gvl(&ruby, input, &options)?;
+    assert_eq!(vec![Some(1.5), None], output);
+
+    let options = DeserializeOptions::new().non_finite_floats(NonFiniteFloats::Error);
+    assert_eq!(
+        "FloatDomainError: NaN",
+        deserialize_without_gvl::<_, Vec<f64>>(&ruby, input, &options)
+            .unwrap_err()
+            .to_string()
+    );
+
+    Ok(())
+}
@@ -9,5 +9,11 @@ fn test_deserializing_vecs() -> Result<(), Error> {
     let output: Vec<i64> = deserialize(&ruby, input)?;
     assert_eq!(&[1, 2, 3], &output[..]);
 
+    // The array's length is passed to serde, so the vector is allocated once at that size.
+    let input: RArray = eval!(&ruby, "(1..1000).to_a")?;
+    let output: Vec<i64> = deserialize(&ruby, input)?;
+    assert_eq!(1000, output.len());
+    assert_eq!(1000, output.capacity());
+
     Ok(())
 }
@@ -0,0 +1,128 @@
+use magnus::{eval, value::ReprValue, Error, RArray, RHash, Value};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_magnus::{deserialize_detailed, to_tree, DeserializeOptions, ErrorKind, PathSegment};
+use std::{collections::HashMap, thread};
+
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+#[serde(deny_unknown_fields)]
+struct A {
+    message: String,
+    count: u64,
+}
+
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+enum B {
+    A(u64),
+}
+
+struct Unsupported;
+
+impl Serialize for Unsupported {
+    fn serialize<S: Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+        Err(serde::ser::Error::custom("unsupported"))
+    }
+}
+
+#[test]
+fn test_inspecting_errors() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+    let options = DeserializeOptions::new();
+
+    let input: RArray = eval!(
+        &ruby,
+        "[{ message: 'Hello', count: 1 }, { message: 2, count: 1 }]"
+    )?;
+    let error = deserialize_detailed::<_, Vec<A>>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::InvalidType, error.kind());
+    assert_eq!(
+        &[PathSegment::Index(1), PathSegment::Key("message".into())],
+        error.path()
+    );
+    assert_eq!(Some("a string"), error.expected());
+    assert_eq!(Some("integer `2`"), error.unexpected());
+
+    let input: RHash = eval!(&ruby, "{ message: 'Hello' }")?;
+    let error = deserialize_detailed::<_, A>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::MissingField, error.kind());
+    assert_eq!(Some("count"), error.expected());
+    assert_eq!("RuntimeError: missing field `count`", error.to_string());
+
+    let input: RHash = eval!(&ruby, "{ message: 'Hello', count: 1, cuont: 2 }")?;
+    let error = deserialize_detailed::<_, A>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::UnknownField, error.kind());
+    assert_eq!(Some("cuont"), error.unexpected());
+    assert_eq!(Some("`message` or `count`"), error.expected());
+
+    let input: RArray = eval!(&ruby, "[1, 2]")?;
+    let error = deserialize_detailed::<_, (u64, u64, u64)>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::InvalidLength, error.kind());
+    assert_eq!(Some("2"), error.unexpected());
+
+    let input: RHash = eval!(&ruby, "{ 'a' => [1], 'b' => [2, 'three'] }")?;
+    let error =
+        deserialize_detailed::<_, HashMap<String, Vec<u64>>>(&ruby, input, &options).unwrap_err();
+    assert_eq!(
+        &[PathSegment::Key("b".into()), PathSegment::Index(1)],
+        error.path()
+    );
+
+    let input: Value = eval!(&ruby, "Object.new")?;
+    let error = deserialize_detailed::<_, u64>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::InvalidType, error.kind());
+
+    // Errors convert to Ruby exceptions of the class their kind calls for.
+    let input: RArray = eval!(&ruby, "[1, 2]")?;
+    let error = deserialize_detailed::<_, (u64, u64, u64)>(&ruby, input, &options).unwrap_err();
+    let error = Error::from(error);
+    assert!(error.is_kind_of(ruby.exception_runtime_error()));
+    assert_eq!(
+        "RuntimeError: invalid length 2, expected a tuple of size 3",
+        error.to_string()
+    );
+
+    let input: RHash = eval!(&ruby, "{ A: 1, B: 2 }")?;
+    let error = deserialize_detailed::<_, B>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::InvalidLength, error.kind());
+    let error = Error::from(error);
+    assert!(error.is_kind_of(ruby.exception_type_error()));
+
+    // Exceptions raised by Ruby methods called during deserialization, here `Hash#keys` while
+    // deserializing an enum, are re-raised as they are, with their backtraces.
+    let input: RHash = eval!(
+        &ruby,
+        r#"
+        $error = ArgumentError.new("raised by keys")
+
+        Class.new(Hash) do
+          def keys
+            raise $error
+          end
+        end.new.merge!(A: 1)
+        "#
+    )?;
+    let error = deserialize_detailed::<_, B>(&ruby, input, &options).unwrap_err();
+    assert_eq!(ErrorKind::RubyException, error.kind());
+    let error = Error::from(error);
+    let raised: Value = ruby.eval("$error")?;
+    assert!(error
+        .value()
+        .unwrap()
+        .funcall::<_, _, bool>("equal?", (raised,))?);
+    assert!(raised
+        .funcall::<_, _, Option<RArray>>("backtrace", ())?
+        .is_some());
+
+    // Errors can be created and inspected on threads that aren't Ruby's.
+    thread::spawn(|| {
+        let error = to_tree(&vec![Unsupported]).unwrap_err();
+        assert_eq!(ErrorKind::Custom, error.kind());
+        assert!(error.path().is_empty());
+        assert_eq!("RuntimeError: unsupported", error.to_string());
+    })
+    .join()
+    .unwrap();
+
+    Ok(())
+}
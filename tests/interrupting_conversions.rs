@@ -0,0 +1,75 @@
+use magnus::{Error, RArray, RHash, Value};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_magnus::{deserialize, serialize, serialize_without_gvl, SerializeOptions};
+use std::{
+    collections::HashMap,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A number that's slow to convert. Converting thousands of them takes long enough to be
+/// interrupted, but not forever, so a conversion that isn't interrupted fails the test rather
+/// than hanging it.
+struct Slow(u64);
+
+impl Serialize for Slow {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        thread::sleep(Duration::from_micros(50));
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Slow {
+    fn deserialize<D>(deserializer: D) -> Result<Slow, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        thread::sleep(Duration::from_micros(50));
+        u64::deserialize(deserializer).map(Slow)
+    }
+}
+
+/// Raises in the main thread from another, once the main thread lets it run.
+const INTERRUPT: &str = r#"
+main = Thread.current
+Thread.new { main.raise "interrupted" }
+"#;
+
+#[test]
+fn test_interrupting_conversions() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+
+    let input: Vec<Slow> = (0..20_000).map(Slow).collect();
+    let _: Value = ruby.eval(INTERRUPT)?;
+    let error = serialize::<_, Value>(&ruby, &input).unwrap_err();
+    assert!(error.is_kind_of(ruby.exception_runtime_error()));
+    assert!(error.to_string().contains("interrupted"));
+
+    // Without the GVL, the interrupt cancels the conversion rather than waiting for it, which
+    // would take ten seconds.
+    let input: Vec<Slow> = (0..200_000).map(Slow).collect();
+    let started = Instant::now();
+    let _: Value = ruby.eval(INTERRUPT)?;
+    let error =
+        serialize_without_gvl::<_, Value>(&ruby, &input, &SerializeOptions::new()).unwrap_err();
+    assert!(error.is_kind_of(ruby.exception_runtime_error()));
+    assert!(error.to_string().contains("interrupted"));
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    let input: RArray = ruby.eval("(0...20_000).to_a")?;
+    let _: Value = ruby.eval(INTERRUPT)?;
+    let error = deserialize::<_, Vec<Slow>>(&ruby, input).unwrap_err();
+    assert!(error.is_kind_of(ruby.exception_runtime_error()));
+    assert!(error.to_string().contains("interrupted"));
+
+    let input: RHash = ruby.eval("(0...20_000).to_h { |i| [i, i] }")?;
+    let _: Value = ruby.eval(INTERRUPT)?;
+    let error = deserialize::<_, HashMap<u64, Slow>>(&ruby, input).unwrap_err();
+    assert!(error.is_kind_of(ruby.exception_runtime_error()));
+    assert!(error.to_string().contains("interrupted"));
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use magnus::{eval, Error, RArray, RHash};
+use serde::Deserialize;
+use serde_magnus::{deserialize_reporting_ignored, DeserializeOptions, PathSegment};
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct Contact {
+    name: String,
+    email_address: Option<String>,
+    tags: Vec<Tag>,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct Tag {
+    label: String,
+}
+
+#[test]
+fn test_reporting_ignored_keys() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+    let options = DeserializeOptions::new();
+
+    let input: RHash = eval!(
+        &ruby,
+        r#"
+        {
+          name: "Alice",
+          "emial_address" => "alice@example.com",
+          tags: [{ label: "friend" }, { label: "work", color: "blue" }],
+          notes: { first: "Met at a conference" }
+        }
+        "#
+    )?;
+    let (output, ignored) = deserialize_reporting_ignored::<_, Contact>(&ruby, input, &options)?;
+    assert_eq!(
+        Contact {
+            name: "Alice".into(),
+            email_address: None,
+            tags: vec![
+                Tag {
+                    label: "friend".into()
+                },
+                Tag {
+                    label: "work".into()
+                }
+            ]
+        },
+        output
+    );
+    assert_eq!(
+        vec![
+            vec![
+                PathSegment::Key("tags".into()),
+                PathSegment::Index(1),
+                PathSegment::Key("color".into())
+            ],
+            vec![PathSegment::Key("emial_address".into())],
+            vec![PathSegment::Key("notes".into())],
+        ],
+        ignored
+    );
+
+    let input: RArray = eval!(&ruby, r#"[{ label: "friend" }]"#)?;
+    let (_, ignored) = deserialize_reporting_ignored::<_, Vec<Tag>>(&ruby, input, &options)?;
+    assert!(ignored.is_empty());
+
+    Ok(())
+}
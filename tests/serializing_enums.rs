@@ -16,6 +16,7 @@ fn test_serializing_enums() -> Result<(), Error> {
 
     let output: RString = serialize(&ruby, &A::A)?;
     assert!(eval!(&ruby, "output == 'A'", output)?);
+    assert!(eval!(&ruby, "output.frozen?", output)?);
 
     let input = A::B(123);
     let output: RHash = serialize(&ruby, &input)?;
@@ -1,5 +1,7 @@
-use magnus::{Error, Float};
-use serde_magnus::serialize;
+use magnus::{value::ReprValue, Error, Float, RArray, Value};
+use serde_magnus::{
+    serialize, serialize_detailed, serialize_with, ErrorKind, NonFiniteFloats, SerializeOptions,
+};
 
 #[test]
 fn test_serializing_floats() -> Result<(), Error> {
@@ -8,5 +10,26 @@ fn test_serializing_floats() -> Result<(), Error> {
     let float: Float = serialize(&ruby, &1.23)?;
     assert_eq!(1.23, float.to_f64());
 
+    let float: Float = serialize(&ruby, &f64::NAN)?;
+    assert!(float.to_f64().is_nan());
+
+    let float: Float = serialize(&ruby, &f32::NEG_INFINITY)?;
+    assert_eq!(f64::NEG_INFINITY, float.to_f64());
+
+    let options = SerializeOptions::new().non_finite_floats(NonFiniteFloats::Nil);
+    let array: RArray = serialize_with(&ruby, &[1.5, f64::NAN, f64::INFINITY], &options)?;
+    assert!(array.entry::<Value>(1)?.is_nil());
+    assert!(array.entry::<Value>(2)?.is_nil());
+    assert_eq!(1.5, array.entry::<f64>(0)?);
+
+    let options = SerializeOptions::new().non_finite_floats(NonFiniteFloats::Error);
+    let error =
+        serialize_detailed::<_, Value>(&ruby, &[1.5, f64::NEG_INFINITY], &options).unwrap_err();
+    assert_eq!(ErrorKind::InvalidValue, error.kind());
+    assert_eq!("FloatDomainError: -Infinity", error.to_string());
+
+    let float: Float = serialize_with(&ruby, &1.5f32, &options)?;
+    assert_eq!(1.5, float.to_f64());
+
     Ok(())
 }
@@ -1,6 +1,6 @@
-use magnus::{encoding::EncodingCapable, Error, RString};
+use magnus::{encoding::EncodingCapable, eval, value::ReprValue, Error, RArray, RString};
 use serde_bytes::{ByteBuf, Bytes};
-use serde_magnus::serialize;
+use serde_magnus::{serialize, serialize_with, SerializeOptions};
 
 #[test]
 fn test_serializing_strings() -> Result<(), Error> {
@@ -26,5 +26,18 @@ fn test_serializing_strings() -> Result<(), Error> {
     assert_eq!(b"Hello, world!", unsafe { output.as_slice() });
     assert!(output.enc_get() == ruby.ascii8bit_encindex());
 
+    let output: RString = serialize(&ruby, &"Hello, world!")?;
+    assert!(!output.is_frozen());
+
+    let options = SerializeOptions::new().intern_strings(true);
+
+    let output: RString = serialize_with(&ruby, &"Hello, world!", &options)?;
+    assert_eq!("Hello, world!", output.to_string()?);
+    assert!(output.enc_get() == ruby.utf8_encindex());
+    assert!(output.is_frozen());
+
+    let output: RArray = serialize_with(&ruby, &["Hello, world!", "Hello, world!"], &options)?;
+    assert!(eval!(&ruby, "output[0].equal?(output[1])", output)?);
+
     Ok(())
 }
@@ -16,6 +16,12 @@ struct D {
     message: String,
 }
 
+#[derive(Serialize)]
+struct E {
+    count: u64,
+    message: &'static str,
+}
+
 #[test]
 fn test_serializing_structs() -> Result<(), Error> {
     let ruby = unsafe { magnus::embed::init() };
@@ -45,5 +51,43 @@ fn test_serializing_structs() -> Result<(), Error> {
         output
     )?);
 
+    let input = vec![
+        D {
+            message: "Hello, world!".into(),
+        },
+        D {
+            message: "Goodbye!".into(),
+        },
+    ];
+    let output: RArray = serialize(&ruby, &input)?;
+    assert!(eval!(
+        &ruby,
+        "output == [ { message: 'Hello, world!' }, { message: 'Goodbye!' } ]",
+        output
+    )?);
+
+    // Field names are cached by address, so the same name in different structs is looked up
+    // separately, but still gives the same Symbol.
+    let input = (
+        D {
+            message: "Hello, world!".into(),
+        },
+        E {
+            count: 1,
+            message: "Goodbye!",
+        },
+    );
+    let output: RArray = serialize(&ruby, &input)?;
+    assert!(eval!(
+        &ruby,
+        "output == [ { message: 'Hello, world!' }, { count: 1, message: 'Goodbye!' } ]",
+        output
+    )?);
+    assert!(eval!(
+        &ruby,
+        "output[0].keys[0].equal?(output[1].keys[1])",
+        output
+    )?);
+
     Ok(())
 }
@@ -0,0 +1,140 @@
+use magnus::{eval, value::ReprValue, Error, RArray, RHash, RString, Value};
+use serde::Serialize;
+use serde_magnus::{
+    materialize, serialize_without_gvl, to_tree, NonFiniteFloats, SerializeOptions, Tree,
+};
+use std::collections::BTreeMap;
+
+#[derive(Serialize)]
+enum A {
+    A,
+    B(u64),
+    C(u64, bool, String),
+    D { message: String },
+}
+
+#[derive(Serialize)]
+struct B {
+    message: String,
+    data: serde_bytes::ByteBuf,
+    count: Option<u64>,
+}
+
+/// Fails to serialize, as a type might when it's invalid.
+struct Invalid;
+
+impl Serialize for Invalid {
+    fn serialize<S>(&self, _serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        Err(serde::ser::Error::custom("invalid"))
+    }
+}
+
+#[test]
+fn test_serializing_trees() -> Result<(), Error> {
+    let ruby = unsafe { magnus::embed::init() };
+    let options = SerializeOptions::default();
+
+    let tree = to_tree(&u64::MAX)?;
+    assert_eq!(Tree::UnsignedInteger(u64::MAX), tree);
+
+    let tree = to_tree(&vec![A::A, A::B(123)])?;
+    assert_eq!(
+        Tree::Array(vec![
+            Tree::InternedString("A".into()),
+            Tree::Hash(vec![(Tree::InternedString("B".into()), Tree::Integer(123))])
+        ]),
+        tree
+    );
+
+    let input = vec![
+        A::A,
+        A::B(123),
+        A::C(123, true, "Hello, world!".into()),
+        A::D {
+            message: "Hello, world!".into(),
+        },
+    ];
+    let tree = std::thread::spawn(move || to_tree(&input).unwrap())
+        .join()
+        .unwrap();
+    let output: RArray = materialize(&ruby, &tree, &options)?;
+    assert!(eval!(
+        &ruby,
+        r#"
+        output == [
+          "A",
+          { "B" => 123 },
+          { "C" => [ 123, true, "Hello, world!" ] },
+          { "D" => { message: "Hello, world!" } }
+        ]
+        "#,
+        output
+    )?);
+
+    let output: RString = materialize(&ruby, &Tree::String("Hello, world!".into()), &options)?;
+    assert!(!output.is_frozen());
+
+    let output: RString = materialize(
+        &ruby,
+        &Tree::InternedString("Hello, world!".into()),
+        &options,
+    )?;
+    assert!(output.is_frozen());
+
+    let input = B {
+        message: "Hello, world!".into(),
+        data: serde_bytes::ByteBuf::from(*b"\xff"),
+        count: None,
+    };
+    let output: RHash = serialize_without_gvl(&ruby, &input, &options)?;
+    assert!(eval!(
+        &ruby,
+        r#"output == { message: "Hello, world!", data: "\xff".b, count: nil }"#,
+        output
+    )?);
+
+    let mut input = BTreeMap::new();
+    input.insert("Yes", "No");
+    input.insert("Stop", "Go");
+    let output: RHash = serialize_without_gvl(&ruby, &input, &options)?;
+    assert!(eval!(
+        &ruby,
+        r#"output == { "Stop" => "Go", "Yes" => "No" }"#,
+        output
+    )?);
+
+    let nil_options = SerializeOptions::new().non_finite_floats(NonFiniteFloats::Nil);
+    let output: RArray = serialize_without_gvl(&ruby, &[1.5, f64::NAN], &nil_options)?;
+    assert!(eval!(&ruby, "output == [1.5, nil]", output)?);
+
+    let error_options = SerializeOptions::new().non_finite_floats(NonFiniteFloats::Error);
+    let output: Result<RArray, Error> =
+        serialize_without_gvl(&ruby, &[f64::INFINITY], &error_options);
+    assert_eq!(
+        "FloatDomainError: Infinity",
+        output.unwrap_err().to_string()
+    );
+
+    let tree = to_tree(&f64::NAN)?;
+    let output: Value = materialize(&ruby, &tree, &nil_options)?;
+    assert!(output.is_nil());
+
+    // Arrays are built in bulk from elements held on the stack, where the garbage collector
+    // must see them.
+    let input: Vec<String> = (0..200).map(|i| i.to_string()).collect();
+    let tree = to_tree(&input)?;
+    let _: Value = ruby.eval("GC.stress = true")?;
+    let output: Result<RArray, Error> = materialize(&ruby, &tree, &options);
+    let _: Value = ruby.eval("GC.stress = false")?;
+    let output = output?;
+    assert!(eval!(&ruby, "output == (0...200).map(&:to_s)", output)?);
+
+    // The error is created without the GVL and raised once it's reacquired.
+    let output: Result<RArray, Error> = serialize_without_gvl(&ruby, &vec![Invalid], &options);
+    assert_eq!("RuntimeError: invalid", output.unwrap_err().to_string());
+
+    Ok(())
+}